@@ -0,0 +1,402 @@
+use std::{cmp::Ordering, fmt::{Display, Write}, sync::Arc};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{scheme::{CommitInfo, ConfigInc, VersionScheme}, VersionComponents, VersionOp};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token
+{
+    /// full year, e.g. `2024`
+    Year,
+    /// short year, e.g. `24` or `106`
+    ShortYear,
+    /// zero padded short year, e.g. `06`
+    PaddedYear,
+    Month,
+    PaddedMonth,
+    /// week of the year counted from january 1st (days 1 to 7 are week 1, december 31st is in week 53),
+    /// unlike ISO weeks it never belongs to the neighbouring year
+    Week,
+    PaddedWeek,
+    Day,
+    PaddedDay,
+    /// counter that resets whenever the date segments change
+    Counter
+}
+
+impl Token
+{
+    fn parse(s: &str) -> Result<Self>
+    {
+        Ok(match s
+        {
+            "YYYY" => Token::Year,
+            "YY" => Token::ShortYear,
+            "0Y" => Token::PaddedYear,
+            "MM" => Token::Month,
+            "0M" => Token::PaddedMonth,
+            "WW" => Token::Week,
+            "0W" => Token::PaddedWeek,
+            "DD" => Token::Day,
+            "0D" => Token::PaddedDay,
+            "patch" | "build" => Token::Counter,
+            _ => bail!("unknown calver format token '{s}', expected one of YYYY, YY, 0Y, MM, 0M, WW, 0W, DD, 0D, patch, build")
+        })
+    }
+
+    fn value(self, date: &CalDate) -> u32
+    {
+        match self
+        {
+            Token::Year => date.year,
+            Token::ShortYear | Token::PaddedYear => date.year.saturating_sub(2000),
+            Token::Month | Token::PaddedMonth => date.month,
+            Token::Week | Token::PaddedWeek => (date.ordinal() - 1) / 7 + 1,
+            Token::Day | Token::PaddedDay => date.day,
+            Token::Counter => 0,
+        }
+    }
+
+    fn width(self) -> usize
+    {
+        match self
+        {
+            Token::PaddedYear | Token::PaddedMonth | Token::PaddedWeek | Token::PaddedDay => 2,
+            _ => 0
+        }
+    }
+}
+
+/// a calendar date
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalDate
+{
+    pub year: u32,
+    pub month: u32,
+    pub day: u32
+}
+
+impl CalDate
+{
+    /// the local date of a point in time given as seconds since the unix epoch and a timezone offset
+    pub fn from_unix(time: i64, offset_minutes: i32) -> Self
+    {
+        // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = (time + i64::from(offset_minutes) * 60).div_euclid(86400);
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self
+        {
+            year: year.max(0) as u32,
+            month: month as u32,
+            day: day as u32
+        }
+    }
+
+    fn is_leap_year(&self) -> bool
+    {
+        (self.year.is_multiple_of(4) && !self.year.is_multiple_of(100)) || self.year.is_multiple_of(400)
+    }
+
+    /// day of the year, starting at 1
+    pub fn ordinal(&self) -> u32
+    {
+        const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let leap_day = u32::from(self.is_leap_year() && self.month > 2);
+        DAYS_BEFORE_MONTH[(self.month.clamp(1, 12) - 1) as usize] + leap_day + self.day
+    }
+}
+
+impl Display for CalDate
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_fmt(format_args!("{:04}-{:02}-{:02}", self.year, self.month, self.day))
+    }
+}
+
+/// a calendar versioning format like `YYYY.0M.patch` or `YY.0W.build`
+#[derive(Debug)]
+pub struct CalVerFormat
+{
+    format: String,
+    tokens: Vec<Token>
+}
+
+impl CalVerFormat
+{
+    pub fn parse(format: &str) -> Result<Self>
+    {
+        let tokens = format.split('.').map(Token::parse).collect::<Result<Vec<_>>>()?;
+
+        if tokens.iter().filter(|t| **t == Token::Counter).count() != 1 || tokens.last() != Some(&Token::Counter)
+        {
+            bail!("invalid calver format '{format}': the last segment must be the counter (patch or build)");
+        }
+
+        if tokens.len() < 2
+        {
+            bail!("invalid calver format '{format}': at least one date segment is required");
+        }
+
+        Ok(Self
+        {
+            format: format.into(),
+            tokens
+        })
+    }
+
+    fn date_tokens(&self) -> &[Token]
+    {
+        &self.tokens[..self.tokens.len() - 1]
+    }
+
+    /// the values of all date segments for `date`
+    fn date_segments(&self, date: &CalDate) -> Arc<[u32]>
+    {
+        self.date_tokens().iter().map(|t| t.value(date)).collect()
+    }
+}
+
+impl Display for CalVerFormat
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str(&self.format)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CalVersion
+{
+    format: Arc<CalVerFormat>,
+    date: Arc<[u32]>,
+    counter: u32,
+    label: Option<Arc<str>>
+}
+
+impl CalVersion
+{
+    /// values of the date segments in the order of the format
+    pub fn date(&self) -> &[u32] {
+        &self.date
+    }
+
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+impl PartialEq for CalVersion
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.date == other.date && self.counter == other.counter && self.label == other.label
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CalVerInc
+{
+    /// increments the counter
+    Counter(u32),
+
+    /// a commit on the given date, increments the counter or resets it if the date segments are newer,
+    /// increments are applied from the tip, so a commit with older date segments does not change the version
+    Commit(CalDate)
+}
+
+impl VersionOp<CalVerInc> for CalVersion
+{
+    fn inc(&mut self, i: &CalVerInc)
+    {
+        match i
+        {
+            CalVerInc::Counter(counter) =>
+            {
+                self.counter += counter;
+            },
+            CalVerInc::Commit(date) =>
+            {
+                let date = self.format.date_segments(date);
+                match date.cmp(&self.date)
+                {
+                    Ordering::Equal => self.counter += 1,
+                    Ordering::Greater =>
+                    {
+                        self.date = date;
+                        self.counter = 0;
+                    },
+                    Ordering::Less => { }
+                }
+            },
+        }
+    }
+}
+
+impl Display for CalVersion
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        for (token, value) in self.format.date_tokens().iter().zip(self.date.iter())
+        {
+            f.write_fmt(format_args!("{value:0width$}.", width = token.width()))?;
+        }
+
+        f.write_str(&self.counter.to_string())?;
+
+        if let Some(ref label) = self.label
+        {
+            f.write_char('-')?;
+            f.write_str(label)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for CalVerInc
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            CalVerInc::Counter(v) => f.write_fmt(format_args!("counter {v:+}")),
+            CalVerInc::Commit(date) => f.write_fmt(format_args!("commit on {date}")),
+        }
+    }
+}
+
+impl VersionComponents for CalVersion
+{
+    fn release(&self) -> Vec<u64>
+    {
+        self.date.iter().map(|v| u64::from(*v)).chain([self.counter.into()]).collect()
+    }
+
+    fn label(&self) -> Option<&str> { self.label() }
+
+    fn build(&self) -> u32 { 0 }
+
+    fn metadata(&self) -> Option<&str> { None }
+}
+
+/// calendar versioning, the date segments are taken from the commit date
+///
+/// every commit increments the counter, which is reset when the date segments change
+#[derive(Clone, Debug)]
+pub struct CalVerScheme
+{
+    format: Arc<CalVerFormat>
+}
+
+impl CalVerScheme
+{
+    pub fn new(format: &str) -> Result<Self>
+    {
+        Ok(Self
+        {
+            format: Arc::new(CalVerFormat::parse(format)?)
+        })
+    }
+}
+
+impl VersionScheme for CalVerScheme
+{
+    type Version = CalVersion;
+    type Inc = CalVerInc;
+
+    /// parses `<segments>[-<label>]`, the number of segments must match the format
+    fn parse(&self, s: &str) -> Result<CalVersion>
+    {
+        let (release, label) = match s.split_once('-')
+        {
+            Some((release, label)) => (release, Some(label)),
+            None => (s, None)
+        };
+
+        let segments = release.split('.').collect::<Vec<_>>();
+        if segments.len() != self.format.tokens.len()
+        {
+            bail!("invalid version '{s}': expected format {}", self.format);
+        }
+
+        let mut values = Vec::with_capacity(segments.len());
+        for segment in segments
+        {
+            if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit())
+            {
+                bail!("invalid version '{s}': '{segment}' is not a number");
+            }
+            let Ok(value) = segment.parse::<u32>() else { bail!("invalid version '{s}': '{segment}' is out of range") };
+            values.push(value);
+        }
+
+        if label.is_some_and(str::is_empty)
+        {
+            bail!("invalid version '{s}': empty label");
+        }
+
+        let counter = values.pop().unwrap_or_default();
+        Ok(CalVersion
+        {
+            format: self.format.clone(),
+            date: values.into(),
+            counter,
+            label: label.map(Into::into)
+        })
+    }
+
+    fn default_basis(&self) -> CalVersion
+    {
+        CalVersion
+        {
+            format: self.format.clone(),
+            date: self.format.date_tokens().iter().map(|_| 0).collect(),
+            counter: 0,
+            label: None
+        }
+    }
+
+    /// the date segments are taken from the commits, so only the counter can be incremented
+    fn config_inc(&self, inc: ConfigInc) -> Result<CalVerInc>
+    {
+        match inc
+        {
+            ConfigInc::Counter(v) => Ok(CalVerInc::Counter(v)),
+            _ => bail!("calver only has the counter increment, expected Counter")
+        }
+    }
+
+    fn commit_inc(&self, commit: &CommitInfo) -> CalVerInc
+    {
+        CalVerInc::Commit(CalDate::from_unix(commit.time, commit.offset_minutes))
+    }
+
+    fn with_label(&self, version: &CalVersion, label: Option<&str>) -> CalVersion
+    {
+        let mut v = version.clone();
+        v.label = label.filter(|label| !label.is_empty()).map(Into::into);
+        v
+    }
+
+    /// the counter is part of the release segments, so there is no build counter to erase
+    fn erase_build(&self, version: &CalVersion) -> CalVersion
+    {
+        version.clone()
+    }
+}
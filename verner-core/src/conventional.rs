@@ -0,0 +1,49 @@
+/// the header of a commit message following the Conventional Commits specification,
+/// e.g. `feat(parser)!: support arrays`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConventionalCommit<'a>
+{
+    pub r#type: &'a str,
+    pub scope: Option<&'a str>,
+    pub description: &'a str,
+
+    /// marked with `!` after the type or scope, or with a `BREAKING CHANGE:` footer
+    pub breaking: bool
+}
+
+impl<'a> ConventionalCommit<'a>
+{
+    /// `None` if the message does not follow the specification
+    pub fn parse(message: &'a str) -> Option<Self>
+    {
+        let header = message.lines().next()?;
+        let (prefix, description) = header.split_once(": ")?;
+
+        let (prefix, breaking) = match prefix.strip_suffix('!')
+        {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false)
+        };
+
+        let (r#type, scope) = match prefix.split_once('(')
+        {
+            Some((r#type, scope)) => (r#type, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None)
+        };
+
+        if r#type.is_empty() || !r#type.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+
+        let breaking = breaking || message.lines().skip(1).any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+
+        Some(Self
+        {
+            r#type,
+            scope,
+            description: description.trim(),
+            breaking
+        })
+    }
+}
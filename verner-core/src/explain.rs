@@ -0,0 +1,79 @@
+use std::fmt::{Display, Write};
+
+use crate::{VersionHint, VersionInc};
+
+/// where a history node came from and why it resolved to its `VersionInc`
+#[derive(Clone)]
+pub struct Origin<Ver, Inc>
+{
+    /// the history node, e.g. a commit id
+    pub node: String,
+
+    /// e.g. `tag v1.0.0` or `build increment`
+    pub reason: String,
+
+    /// the resolution of another history that provided the node's version
+    pub nested: Option<Box<Explanation<Ver, Inc>>>
+}
+
+/// a history that can describe the origin of its items
+pub trait ExplainedHistory<Ver, Inc>: Iterator<Item = anyhow::Result<VersionInc<Ver, Inc>>>
+{
+    /// origin of the item last returned by `next`
+    fn origin(&mut self) -> Origin<Ver, Inc>;
+}
+
+#[derive(Clone)]
+pub struct ExplainStep<Ver, Inc>
+{
+    pub origin: Origin<Ver, Inc>,
+    pub inc: VersionInc<Ver, Inc>
+}
+
+/// the steps of a version resolution, from the tip to the basis
+#[derive(Clone)]
+pub struct Explanation<Ver, Inc>
+{
+    pub steps: Vec<ExplainStep<Ver, Inc>>,
+
+    /// vNext increment applied to the basis, if any
+    pub v_next: Option<Inc>,
+
+    pub version: Ver,
+    pub hint: VersionHint
+}
+
+impl<Ver: Display, Inc: Display> Explanation<Ver, Inc>
+{
+    /// writes one line per step, nested explanations are indented below their step
+    fn write_indented(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result
+    {
+        let pad = " ".repeat(indent);
+        f.write_fmt(format_args!("{pad}{} ({})", self.version, self.hint))?;
+
+        for step in self.steps.iter()
+        {
+            f.write_fmt(format_args!("\n{pad}  {} {}: {}", step.origin.node, step.origin.reason, step.inc))?;
+            if let Some(ref nested) = step.origin.nested
+            {
+                f.write_char('\n')?;
+                nested.write_indented(f, indent + 4)?;
+            }
+        }
+
+        if let Some(ref v_next) = self.v_next
+        {
+            f.write_fmt(format_args!("\n{pad}  vNext: {v_next}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Ver: Display, Inc: Display> Display for Explanation<Ver, Inc>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        self.write_indented(f, 0)
+    }
+}
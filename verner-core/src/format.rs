@@ -0,0 +1,214 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use crate::VersionComponents;
+
+/// version grammars of package ecosystems
+///
+/// all formats are derived from the scheme independent components of a version:
+/// the release segments, the pre-release label, the build counter and the build metadata
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat
+{
+    /// `1.2.0-rc.3+meta`, the release is padded to three segments, further segments are moved to the build metadata
+    Semver,
+
+    /// `1.2.0rc3`, `alpha`/`beta`/`rc` map to pre-releases, other labels to dev releases with a local version label
+    Pep440,
+
+    /// `1.2.0-SNAPSHOT` for snapshot labels, `1.2.0-rc-3` otherwise
+    Maven,
+
+    /// `1.2.0-rc.3+meta`, like semver but keeps a fourth release segment
+    Nuget,
+
+    /// `1.2.0~rc.3+meta`, pre-releases sort before the release
+    Debian,
+
+    /// `1.2.0.3`, four numeric parts: three release segments and the build counter
+    Assembly
+}
+
+impl OutputFormat
+{
+    pub const ALL: [OutputFormat; 6] = [OutputFormat::Semver, OutputFormat::Pep440, OutputFormat::Maven, OutputFormat::Nuget, OutputFormat::Debian, OutputFormat::Assembly];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            OutputFormat::Semver => "semver",
+            OutputFormat::Pep440 => "pep440",
+            OutputFormat::Maven => "maven",
+            OutputFormat::Nuget => "nuget",
+            OutputFormat::Debian => "debian",
+            OutputFormat::Assembly => "assembly",
+        }
+    }
+
+    pub fn format(&self, version: &dyn VersionComponents) -> Result<String>
+    {
+        Ok(match self
+        {
+            OutputFormat::Semver => format_semver(version, 3),
+            OutputFormat::Nuget => format_semver(version, 4),
+            OutputFormat::Pep440 => format_pep440(version),
+            OutputFormat::Maven => format_maven(version),
+            OutputFormat::Debian => format_debian(version),
+            OutputFormat::Assembly => format_assembly(version)?,
+        })
+    }
+}
+
+impl FromStr for OutputFormat
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self>
+    {
+        let Some(format) = Self::ALL.into_iter().find(|f| f.name().eq_ignore_ascii_case(s)) else
+        {
+            bail!("unknown format '{s}', expected one of: {}", Self::ALL.map(|f| f.name()).join(", "));
+        };
+        Ok(format)
+    }
+}
+
+impl Display for OutputFormat
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str(self.name())
+    }
+}
+
+/// release segments padded with zeros to at least `min` and truncated to at most `max` segments
+fn release(version: &dyn VersionComponents, min: usize, max: usize) -> String
+{
+    let mut release = version.release();
+    release.resize(release.len().clamp(min, max), 0);
+    release.iter().map(ToString::to_string).collect::<Vec<_>>().join(".")
+}
+
+/// label and build counter as dot separated pre-release identifiers
+fn pre_release(version: &dyn VersionComponents) -> Option<String>
+{
+    let build = Some(version.build()).filter(|b| *b > 0).map(|b| b.to_string());
+    let parts: Vec<String> = version.label().map(Into::into).into_iter().chain(build).collect();
+    (!parts.is_empty()).then(|| parts.join("."))
+}
+
+/// release segments beyond `max_release` are prepended to the build metadata, e.g. `2024.5.17+2`
+fn format_semver(version: &dyn VersionComponents, max_release: usize) -> String
+{
+    let mut s = release(version, 3, max_release);
+    if let Some(pre) = pre_release(version)
+    {
+        s.push('-');
+        s.push_str(&pre);
+    }
+
+    let metadata: Vec<String> = version.release().iter().skip(max_release).map(ToString::to_string)
+        .chain(version.metadata().map(Into::into))
+        .collect();
+    if !metadata.is_empty()
+    {
+        s.push('+');
+        s.push_str(&metadata.join("."));
+    }
+    s
+}
+
+/// lowercase alphanumeric segments separated by dots, as required by PEP 440 local version labels
+fn pep440_local(s: &str) -> String
+{
+    s.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|p| !p.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn format_pep440(version: &dyn VersionComponents) -> String
+{
+    let mut s = release(version, 1, usize::MAX);
+    let build = version.build();
+    let mut local = Vec::new();
+
+    match version.label().map(str::to_ascii_lowercase).as_deref()
+    {
+        None if build == 0 => {},
+        Some("a" | "alpha") => s.push_str(&format!("a{build}")),
+        Some("b" | "beta") => s.push_str(&format!("b{build}")),
+        Some("c" | "rc" | "pre" | "preview") => s.push_str(&format!("rc{build}")),
+        None | Some("dev" | "snapshot") => s.push_str(&format!(".dev{build}")),
+        Some(_) =>
+        {
+            s.push_str(&format!(".dev{build}"));
+            local.extend(version.label().map(pep440_local));
+        }
+    }
+
+    local.extend(version.metadata().map(pep440_local));
+    local.retain(|l| !l.is_empty());
+    if !local.is_empty()
+    {
+        s.push('+');
+        s.push_str(&local.join("."));
+    }
+
+    s
+}
+
+fn format_maven(version: &dyn VersionComponents) -> String
+{
+    let mut s = release(version, 3, usize::MAX);
+    let build = Some(version.build()).filter(|b| *b > 0);
+
+    match version.label()
+    {
+        Some(label) if label.eq_ignore_ascii_case("snapshot") => s.push_str("-SNAPSHOT"),
+        Some(label) =>
+        {
+            s.push('-');
+            s.push_str(label);
+            build.inspect(|b| s.push_str(&format!("-{b}")));
+        },
+        None => { build.inspect(|b| s.push_str(&format!("-{b}"))); }
+    }
+
+    s
+}
+
+fn format_debian(version: &dyn VersionComponents) -> String
+{
+    let mut s = release(version, 3, usize::MAX);
+
+    // hyphens are reserved for the debian revision
+    if let Some(pre) = pre_release(version)
+    {
+        s.push('~');
+        s.push_str(&pre.replace('-', "."));
+    }
+    if let Some(metadata) = version.metadata()
+    {
+        s.push('+');
+        s.push_str(&metadata.replace('-', "."));
+    }
+    s
+}
+
+fn format_assembly(version: &dyn VersionComponents) -> Result<String>
+{
+    let mut release = version.release();
+    release.resize(3, 0);
+    release.push(version.build().into());
+
+    if let Some(part) = release.iter().find(|p| **p > u64::from(u16::MAX))
+    {
+        bail!("cannot format {version} as assembly version: {part} exceeds {}", u16::MAX);
+    }
+
+    Ok(release.iter().map(ToString::to_string).collect::<Vec<_>>().join("."))
+}
@@ -1,10 +1,20 @@
+#[cfg(test)] mod tests;
+
 use std::fmt::Display;
 
 use anyhow::Result;
+use explain::{ExplainStep, ExplainedHistory, Explanation};
+use serde::Serialize;
 pub mod semver;
+pub mod calver;
+pub mod format;
+pub mod template;
 pub mod output;
+pub mod scheme;
+pub mod explain;
+pub mod conventional;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum VersionInc<Ver, Inc>
 {
     /// increment the version
@@ -23,8 +33,38 @@ pub enum VersionInc<Ver, Inc>
     Skip,
 }
 
+impl<Ver: Display, Inc: Display> Display for VersionInc<Ver, Inc>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            VersionInc::Inc(inc) => f.write_fmt(format_args!("increment by {inc}")),
+            VersionInc::SoftBasis(v) => f.write_fmt(format_args!("soft basis {v}")),
+            VersionInc::HardBasis(v) => f.write_fmt(format_args!("hard basis {v}")),
+            VersionInc::Fixed(v) => f.write_fmt(format_args!("fixed {v}")),
+            VersionInc::Skip => f.write_str("skip"),
+        }
+    }
+}
+
 pub type HistoryIter<'a, Ver, Inc> = dyn Iterator<Item = Result<VersionInc<Ver, Inc>>> + 'a;
 
+/// scheme independent view of a version, used for output
+pub trait VersionComponents: Display + Send + Sync
+{
+    /// numeric release segments, e.g. `[major, minor, patch]`
+    fn release(&self) -> Vec<u64>;
+
+    /// pre-release label without the build counter
+    fn label(&self) -> Option<&str>;
+
+    /// per-commit build counter, `0` if there is none
+    fn build(&self) -> u32;
+
+    fn metadata(&self) -> Option<&str>;
+}
+
 pub trait VersionOp<Inc>
 {
     fn inc(&mut self, i: &Inc);
@@ -35,23 +75,76 @@ pub trait NodeVersionInfo<Inc>
     fn version_effect(&self) -> Inc;
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VersionHint
 {
+    /// no commit changed the basis
     Basis,
+    /// the version is fixed by a tag on the tip
     Fixed,
+    /// the version was incremented by commits since the basis
     Derived
 }
 
+impl Display for VersionHint
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str(match self
+        {
+            VersionHint::Basis => "basis",
+            VersionHint::Fixed => "fixed",
+            VersionHint::Derived => "derived",
+        })
+    }
+}
+
 pub fn resolve_version<Ver: VersionOp<Inc> + Display, Inc: Display>(history: &mut HistoryIter<Ver, Inc>, basis: Ver, v_next: Option<Inc>) -> Result<(Ver, VersionHint)>
+{
+    let (version, hint, _) = resolve(history, basis, v_next, |_, _| {})?;
+    Ok((version, hint))
+}
+
+/// like `resolve_version`, but records every history item with its origin
+pub fn resolve_version_explained<Ver, Inc, H>(history: &mut H, basis: Ver, v_next: Option<Inc>) -> Result<Explanation<Ver, Inc>>
+where
+    Ver: VersionOp<Inc> + Display + Clone,
+    Inc: Display + Clone,
+    H: ExplainedHistory<Ver, Inc>
+{
+    let mut steps = Vec::new();
+    let (version, hint, v_next) = resolve(history, basis, v_next, |history, inc| steps.push(ExplainStep
+    {
+        origin: history.origin(),
+        inc: inc.clone()
+    }))?;
+
+    Ok(Explanation
+    {
+        steps,
+        v_next,
+        version,
+        hint
+    })
+}
+
+/// resolves the version and returns the vNext increment if it was applied, `on_item` is called for every history item
+fn resolve<Ver, Inc, H, F>(history: &mut H, basis: Ver, v_next: Option<Inc>, mut on_item: F) -> Result<(Ver, VersionHint, Option<Inc>)>
+where
+    Ver: VersionOp<Inc>,
+    H: Iterator<Item = Result<VersionInc<Ver, Inc>>> + ?Sized,
+    F: FnMut(&mut H, &VersionInc<Ver, Inc>)
 {
     let mut hint = VersionHint::Basis;
     let mut version = basis;
     let mut incs: Vec<Inc> = Default::default();
+    let mut applied_v_next = None;
 
     while let Some(inc) = history.next()
     {
         let inc = inc?;
+        on_item(history, &inc);
 
         match inc
         {
@@ -63,9 +156,9 @@ pub fn resolve_version<Ver: VersionOp<Inc> + Display, Inc: Display>(history: &mu
             VersionInc::SoftBasis(soft_basis) =>
             {
                 version = soft_basis;
-                if incs.len() > 0
+                if !incs.is_empty()
                 {
-                    v_next.inspect(|i|version.inc(i));
+                    applied_v_next = v_next.inspect(|i|version.inc(i));
                 }
                 break;
             },
@@ -76,22 +169,24 @@ pub fn resolve_version<Ver: VersionOp<Inc> + Display, Inc: Display>(history: &mu
             },
             VersionInc::Fixed(fix) =>
             {
-                if incs.len() == 0
+                if incs.is_empty()
                 {
-                    return Ok((fix, VersionHint::Fixed));
+                    return Ok((fix, VersionHint::Fixed, None));
                 }
                 version = fix;
-                v_next.inspect(|i|version.inc(i));
+                applied_v_next = v_next.inspect(|i|version.inc(i));
                 break;
             },
             VersionInc::Skip => { },
         }
     }
 
-    for i in incs.as_slice()
+    // the history is walked from the tip, apply the oldest increment first:
+    // increments do not commute (a bump resets the build counter) and mainline merges bump in history order
+    for i in incs.iter().rev()
     {
         version.inc(i);
     }
     
-    Ok((version, hint))
+    Ok((version, hint, applied_v_next))
 }
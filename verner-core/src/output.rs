@@ -1,20 +1,16 @@
 use std::fmt::Display;
 
-#[derive(PartialEq, PartialOrd)]
+#[derive(PartialEq, PartialOrd, Default)]
 pub enum LogLevel
 {
     Trace,
+    #[default]
     Info,
     Success,
     Warning,
     Error
 }
 
-impl Default for LogLevel
-{
-    fn default() -> Self { LogLevel::Info }
-}
-
 pub trait ConsoleWriter
 {
     fn user_line<D: Display>(&self, level: LogLevel, d: D);
@@ -0,0 +1,91 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{VersionComponents, VersionOp};
+
+/// the commit a history increment originates from
+#[derive(Clone, Copy, Debug)]
+pub struct CommitInfo
+{
+    /// commit time in seconds since the unix epoch
+    pub time: i64,
+
+    /// timezone offset of the commit time in minutes
+    pub offset_minutes: i32
+}
+
+/// increments of the kinds of change a commit can announce, e.g. with conventional commits
+#[derive(Clone, Debug)]
+pub struct ChangeIncs<Inc>
+{
+    pub breaking: Inc,
+    pub feature: Inc,
+    pub fix: Inc
+}
+
+/// an increment in a config file, e.g. `!Minor 1`, the version scheme decides which kinds it supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigInc
+{
+    Major(u32),
+    Minor(u32),
+    Patch(u32),
+    Build(u32),
+    Counter(u32)
+}
+
+/// a versioning scheme, e.g. SemVer
+///
+/// the git solver is generic over the scheme, it only needs to know how versions are parsed,
+/// which increments exist and how a commit without special meaning affects the version
+pub trait VersionScheme
+{
+    type Version: VersionOp<Self::Inc> + VersionComponents + Clone + Display + 'static;
+
+    /// increment kinds of the scheme
+    type Inc: Clone + Display + Serialize + DeserializeOwned;
+
+    fn parse(&self, s: &str) -> Result<Self::Version>;
+
+    /// the text of a version, e.g. the `version` output variable
+    fn format(&self, version: &Self::Version) -> String
+    {
+        version.to_string()
+    }
+
+    /// the version used if no basis could be found
+    fn default_basis(&self) -> Self::Version;
+
+    /// the increment of a config file increment (e.g. `v_next`), fails if the scheme has no such increment
+    fn config_inc(&self, inc: ConfigInc) -> Result<Self::Inc>;
+
+    /// the increment of a commit that has no special meaning
+    fn commit_inc(&self, commit: &CommitInfo) -> Self::Inc;
+
+    fn with_label(&self, version: &Self::Version, label: Option<&str>) -> Self::Version;
+
+    /// removes the per-commit build counter from the version
+    fn erase_build(&self, version: &Self::Version) -> Self::Version;
+
+    /// the default increments of changes, `None` if the scheme has none and they have to be configured
+    fn change_incs(&self) -> Option<ChangeIncs<Self::Inc>>
+    {
+        None
+    }
+
+    /// orders versions by precedence, a version without label ranks above the same release with a label
+    fn cmp_precedence(&self, a: &Self::Version, b: &Self::Version) -> Ordering
+    {
+        a.release().cmp(&b.release())
+            .then_with(|| match (a.label(), b.label())
+            {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b)
+            })
+            .then_with(|| a.build().cmp(&b.build()))
+    }
+}
@@ -1,48 +1,235 @@
-use std::{fmt::{Display, Write}, rc::Rc};
+use std::{cmp::Ordering, fmt::{Display, Write}, str::FromStr, sync::Arc};
 
+use anyhow::{bail, Result};
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::VersionOp;
+use crate::{scheme::{ChangeIncs, CommitInfo, ConfigInc, VersionScheme}, VersionComponents, VersionOp};
 
 lazy_static::lazy_static!
 {
-    static ref SEMVER_REGEX: Regex = Regex::new(r"^(?<major>\d+)\.(?<minor>\d+)(?:\.(?<patch>\d+))?(?:-(?<label>[^\.]*)(?:\.(?<build>\d+))?)?$").unwrap();
+    static ref SEMVER_REGEX: Regex = Regex::new(r"^(?<major>\d+)\.(?<minor>\d+)(?:\.(?<patch>\d+))?(?:-(?<pre>[^+]*))?(?:\+(?<metadata>.*))?$").unwrap();
 }
 
-#[derive(Clone, Default, Debug, PartialEq)]
+/// a single dot separated pre-release identifier
+///
+/// the derived ordering follows SemVer: numeric identifiers compare numerically and have
+/// lower precedence than alphanumeric identifiers, which compare lexically in ASCII sort order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Identifier<'a>
+{
+    Numeric(u64),
+    AlphaNumeric(&'a str)
+}
+
+impl<'a> Identifier<'a>
+{
+    fn new(s: &'a str) -> Self
+    {
+        if s.bytes().all(|b| b.is_ascii_digit())
+        {
+            if let Ok(n) = s.parse()
+            {
+                return Identifier::Numeric(n);
+            }
+        }
+
+        Identifier::AlphaNumeric(s)
+    }
+}
+
+impl Display for Identifier<'_>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Identifier::Numeric(n) => n.fmt(f),
+            Identifier::AlphaNumeric(s) => f.write_str(s),
+        }
+    }
+}
+
+/// custom precedence of alphanumeric pre-release identifiers, e.g. `alpha < beta < rc`
+///
+/// listed identifiers compare by their position in the list and have a higher precedence than
+/// identifiers that are not listed, which fall back to the SemVer ordering
+#[derive(Clone, Default, Debug)]
+pub struct LabelPrecedence
+{
+    labels: Vec<String>
+}
+
+impl LabelPrecedence
+{
+    pub fn new(labels: Vec<String>) -> Self
+    {
+        Self { labels }
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    fn position(&self, identifier: &str) -> Option<usize>
+    {
+        self.labels.iter().position(|l| l == identifier)
+    }
+
+    pub fn cmp_identifiers(&self, a: &Identifier, b: &Identifier) -> Ordering
+    {
+        let (Identifier::AlphaNumeric(a_str), Identifier::AlphaNumeric(b_str)) = (a, b) else { return a.cmp(b) };
+
+        match (self.position(a_str), self.position(b_str))
+        {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => a.cmp(b)
+        }
+    }
+
+    /// compares the precedence of two versions, build metadata is ignored
+    pub fn cmp(&self, a: &SemVersion, b: &SemVersion) -> Ordering
+    {
+        a.major.cmp(&b.major)
+            .then(a.minor.cmp(&b.minor))
+            .then(a.patch.cmp(&b.patch))
+            .then_with(|| match (a.is_pre_release(), b.is_pre_release())
+            {
+                (false, false) => Ordering::Equal,
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+                (true, true) =>
+                {
+                    let mut a = a.pre_release();
+                    let mut b = b.pre_release();
+                    loop
+                    {
+                        match (a.next(), b.next())
+                        {
+                            (None, None) => return Ordering::Equal,
+                            (None, Some(_)) => return Ordering::Less,
+                            (Some(_), None) => return Ordering::Greater,
+                            (Some(a), Some(b)) => match self.cmp_identifiers(&a, &b)
+                            {
+                                Ordering::Equal => continue,
+                                ord => return ord
+                            }
+                        }
+                    }
+                }
+            })
+    }
+}
+
+#[derive(Clone, Default, Debug)]
 pub struct SemVersion
 {
     major: u32,
     minor: u32,
     patch: u32,
     build: u32,
-    label: Option<Rc<Box<String>>>
+    label: Option<Arc<str>>,
+    metadata: Option<Arc<str>>
+}
+
+fn parse_number<T: FromStr>(version: &str, what: &str, s: &str) -> Result<T>
+{
+    if s.len() > 1 && s.starts_with('0')
+    {
+        bail!("invalid version '{version}': {what} '{s}' must not contain leading zeros");
+    }
+
+    let Ok(n) = s.parse() else { bail!("invalid version '{version}': {what} '{s}' is out of range") };
+    Ok(n)
+}
+
+fn validate_identifiers(version: &str, what: &str, s: &str, allow_leading_zeros: bool) -> Result<()>
+{
+    for identifier in s.split('.')
+    {
+        if identifier.is_empty()
+        {
+            bail!("invalid version '{version}': {what} contains an empty identifier");
+        }
+
+        if let Some(c) = identifier.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-')
+        {
+            bail!("invalid version '{version}': {what} identifier '{identifier}' contains the invalid character '{c}'");
+        }
+
+        let numeric = identifier.bytes().all(|b| b.is_ascii_digit());
+        if !allow_leading_zeros && numeric && identifier.len() > 1 && identifier.starts_with('0')
+        {
+            bail!("invalid version '{version}': numeric {what} identifier '{identifier}' must not contain leading zeros");
+        }
+    }
+
+    Ok(())
 }
 
 impl SemVersion
 {
-    pub fn parse(s: &str) -> Option<Self>
+    /// parses a SemVer 2.0.0 version string (the patch component may be omitted)
+    ///
+    /// a trailing, non-zero numeric pre-release identifier is used as the build counter
+    pub fn parse(s: &str) -> Result<Self>
     {
-        if let Some(captures) = SEMVER_REGEX.captures(s)
+        let Some(captures) = SEMVER_REGEX.captures(s) else
+        {
+            bail!("invalid version '{s}': expected <major>.<minor>.<patch>[-<pre-release>][+<build metadata>]");
+        };
+
+        let mut version = Self
         {
-            return Some(Self
+            major: parse_number(s, "major version", &captures["major"])?,
+            minor: parse_number(s, "minor version", &captures["minor"])?,
+            patch: captures.name("patch").map_or(Ok(0), |p| parse_number(s, "patch version", p.as_str()))?,
+            ..Default::default()
+        };
+
+        if let Some(pre) = captures.name("pre").map(|p| p.as_str())
+        {
+            validate_identifiers(s, "pre-release", pre, false)?;
+
+            let (label, last) = match pre.rsplit_once('.')
             {
-                major: captures["major"].parse().unwrap(),
-                minor: captures["minor"].parse().unwrap(),
-                patch: captures.name("patch").map_or(0, |s| s.as_str().parse().unwrap()),
-                build: captures.name("build").map_or(0, |s| s.as_str().parse().unwrap()),
-                label: captures.name("label").filter(|t| t.len() > 0).map(|s| Rc::new(Box::new(s.as_str().to_string())))
-            })
+                Some((label, last)) => (Some(label), last),
+                None => (None, pre)
+            };
+
+            match last.parse::<u32>()
+            {
+                Ok(build) if build > 0 =>
+                {
+                    version.build = build;
+                    version.label = label.map(Into::into);
+                },
+                _ => version.label = Some(pre.into())
+            }
         }
 
-        None
+        if let Some(metadata) = captures.name("metadata").map(|m| m.as_str())
+        {
+            validate_identifiers(s, "build metadata", metadata, true)?;
+            version.metadata = Some(metadata.into());
+        }
+
+        Ok(version)
     }
 
     pub fn with_label(&self, label: Option<String>) -> SemVersion
     {
         let mut v = self.clone();
-        v.label = label.map(|label| Rc::new(Box::new(label.to_string())));
+        v.label = label.filter(|label| !label.is_empty()).map(Into::into);
+        v
+    }
+
+    pub fn with_metadata(&self, metadata: Option<String>) -> SemVersion
+    {
+        let mut v = self.clone();
+        v.metadata = metadata.filter(|metadata| !metadata.is_empty()).map(Into::into);
         v
     }
     
@@ -62,14 +249,61 @@ impl SemVersion
         self.build
     }
     
-    pub fn label<'a>(&'a self) -> Option<&'a str> {
-        self.label.as_ref().map(|r| r.as_str())
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn metadata(&self) -> Option<&str> {
+        self.metadata.as_deref()
+    }
+
+    /// all pre-release identifiers, including the build counter
+    pub fn pre_release(&self) -> impl Iterator<Item = Identifier<'_>> + '_
+    {
+        let label = self.label().into_iter().flat_map(|l| l.split('.')).map(Identifier::new);
+        let build = Some(self.build).filter(|b| *b > 0).map(|b| Identifier::Numeric(b.into()));
+        label.chain(build)
+    }
+
+    pub fn is_pre_release(&self) -> bool {
+        self.label.is_some() || self.build > 0
+    }
+
+    /// compares the SemVer precedence of two versions, build metadata is ignored
+    pub fn cmp_precedence(&self, other: &Self) -> Ordering
+    {
+        LabelPrecedence::default().cmp(self, other)
     }
     
     pub fn erase_build(&self) -> Self {
         let mut v = self.clone();
         v.build = 0;
-        return v;
+        v
+    }
+}
+
+impl FromStr for SemVersion
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> { Self::parse(s) }
+}
+
+/// serializes as the canonical version string
+impl Serialize for SemVersion
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SemVersion
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error>
+    {
+        let s = String::deserialize(deserializer)?;
+        SemVersion::parse(&s).map_err(de::Error::custom)
     }
 }
 
@@ -124,7 +358,7 @@ impl Display for SemVersion
         f.write_char('.')?;
         f.write_str(&self.patch.to_string())?;
 
-        if self.label.is_some() || self.build > 0
+        if self.is_pre_release()
         {
             f.write_char('-')?;
         }
@@ -136,68 +370,134 @@ impl Display for SemVersion
 
         if self.build > 0
         {
-            f.write_char('.')?;
+            if self.label.is_some()
+            {
+                f.write_char('.')?;
+            }
             f.write_str(&self.build.to_string())?;
         }
 
+        if let Some(ref metadata) = self.metadata
+        {
+            f.write_char('+')?;
+            f.write_str(metadata)?;
+        }
+
         Ok(())
     }
 }
 
+impl PartialEq for SemVersion
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.major == other.major
+            && self.minor == other.minor
+            && self.patch == other.patch
+            && self.pre_release().eq(other.pre_release())
+            && self.metadata == other.metadata
+    }
+}
+
+impl Eq for SemVersion {}
+
 impl PartialOrd for SemVersion
 {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering>
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
     {
-        if self.major > other.major
-        {
-            return Some(std::cmp::Ordering::Greater);
-        }
-        
-        if self.major < other.major
-        {
-            return Some(std::cmp::Ordering::Less)
-        }
+        Some(self.cmp(other))
+    }
+}
 
-        
-        if self.minor > other.minor
-        {
-            return Some(std::cmp::Ordering::Greater);
-        }
-        
-        if self.minor < other.minor
-        {
-            return Some(std::cmp::Ordering::Less)
-        }
-        
+/// orders by SemVer precedence, versions of equal precedence are ordered by their build metadata
+impl Ord for SemVersion
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.cmp_precedence(other).then_with(|| self.metadata.cmp(&other.metadata))
+    }
+}
 
-        if self.patch > other.patch
-        {
-            return Some(std::cmp::Ordering::Greater);
-        }
-        
-        if self.patch < other.patch
-        {
-            return Some(std::cmp::Ordering::Less)
-        }
+impl VersionComponents for SemVersion
+{
+    fn release(&self) -> Vec<u64>
+    {
+        vec![self.major.into(), self.minor.into(), self.patch.into()]
+    }
 
-        if self.label != other.label
-        {
-            return None;
-        }
+    fn label(&self) -> Option<&str> { self.label() }
 
-        
-        if self.build > other.build
-        {
-            return Some(std::cmp::Ordering::Greater);
-        }
-        
-        if self.build < other.build
+    fn build(&self) -> u32 { self.build }
+
+    fn metadata(&self) -> Option<&str> { self.metadata() }
+}
+
+/// SemVer 2.0.0, every commit increments the build counter
+#[derive(Clone, Default, Debug)]
+pub struct SemVerScheme
+{
+    label_precedence: LabelPrecedence
+}
+
+impl SemVerScheme
+{
+    /// pre-release labels are compared by `label_precedence`
+    pub fn new(label_precedence: LabelPrecedence) -> Self
+    {
+        Self { label_precedence }
+    }
+}
+
+impl VersionScheme for SemVerScheme
+{
+    type Version = SemVersion;
+    type Inc = SemVersionInc;
+
+    fn parse(&self, s: &str) -> Result<SemVersion>
+    {
+        SemVersion::parse(s)
+    }
+
+    fn default_basis(&self) -> SemVersion
+    {
+        SemVersion::default()
+    }
+
+    fn config_inc(&self, inc: ConfigInc) -> Result<SemVersionInc>
+    {
+        Ok(match inc
         {
-            return Some(std::cmp::Ordering::Less)
-        }
+            ConfigInc::Major(v) => SemVersionInc::Major(v),
+            ConfigInc::Minor(v) => SemVersionInc::Minor(v),
+            ConfigInc::Patch(v) => SemVersionInc::Patch(v),
+            ConfigInc::Build(v) => SemVersionInc::Build(v),
+            ConfigInc::Counter(_) => bail!("semver has no counter increment, expected Major, Minor, Patch or Build")
+        })
+    }
+
+    fn commit_inc(&self, _commit: &CommitInfo) -> SemVersionInc
+    {
+        SemVersionInc::Build(1)
+    }
 
-        
-        Some(std::cmp::Ordering::Equal)
+    fn with_label(&self, version: &SemVersion, label: Option<&str>) -> SemVersion
+    {
+        version.with_label(label.map(Into::into))
+    }
+
+    fn erase_build(&self, version: &SemVersion) -> SemVersion
+    {
+        version.erase_build()
+    }
+
+    fn change_incs(&self) -> Option<ChangeIncs<SemVersionInc>>
+    {
+        Some(ChangeIncs { breaking: SemVersionInc::Major(1), feature: SemVersionInc::Minor(1), fix: SemVersionInc::Patch(1) })
+    }
+
+    fn cmp_precedence(&self, a: &SemVersion, b: &SemVersion) -> Ordering
+    {
+        self.label_precedence.cmp(a, b)
     }
 }
 
@@ -213,4 +513,4 @@ impl Display for SemVersionInc
             SemVersionInc::Build(v) => f.write_fmt(format_args!("0.0.0-*.{v:+}")),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,169 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part
+{
+    Literal(String),
+    Variable(String)
+}
+
+/// a string with variables, written as `{name}`, `$name` or `${name}`
+///
+/// `{{`, `}}` and `$$` escape the literal characters, `$name` takes the longest
+/// sequence of `[_0-9a-zA-Z]` as the name (like regex replacement strings),
+/// see `parse_replacement` for templates without `{name}`
+#[derive(Clone, Debug)]
+pub struct Template
+{
+    source: String,
+    parts: Vec<Part>
+}
+
+impl Template
+{
+    pub fn parse(s: &str) -> Result<Self>
+    {
+        Self::parse_with(s, true)
+    }
+
+    /// like `parse`, but only `$name`, `${name}` and `$$` are recognized and braces are literal,
+    /// as in the labels and versions of config files
+    pub fn parse_replacement(s: &str) -> Result<Self>
+    {
+        Self::parse_with(s, false)
+    }
+
+    fn parse_with(s: &str, braces: bool) -> Result<Self>
+    {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.char_indices().peekable();
+
+        let read_until_brace = |start: usize, chars: &mut std::iter::Peekable<std::str::CharIndices>| -> Result<String>
+        {
+            let mut name = String::new();
+            for (_, c) in chars.by_ref()
+            {
+                if c == '}'
+                {
+                    if name.is_empty()
+                    {
+                        bail!("invalid template '{s}': empty variable name at {start}");
+                    }
+                    return Ok(name);
+                }
+                name.push(c);
+            }
+            bail!("invalid template '{s}': unterminated variable at {start}");
+        };
+
+        while let Some((i, c)) = chars.next()
+        {
+            let name = match c
+            {
+                '$' => match chars.peek()
+                {
+                    Some((_, '$')) => { chars.next(); literal.push('$'); continue; },
+                    Some((_, '{')) => { chars.next(); read_until_brace(i, &mut chars)? },
+                    _ =>
+                    {
+                        let mut name = String::new();
+                        while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                        {
+                            name.push(c);
+                        }
+                        if name.is_empty()
+                        {
+                            literal.push('$');
+                            continue;
+                        }
+                        name
+                    }
+                },
+                '{' | '}' if !braces => { literal.push(c); continue; },
+                '{' => match chars.peek()
+                {
+                    Some((_, '{')) => { chars.next(); literal.push('{'); continue; },
+                    _ => read_until_brace(i, &mut chars)?
+                },
+                '}' => match chars.peek()
+                {
+                    Some((_, '}')) => { chars.next(); literal.push('}'); continue; },
+                    _ => bail!("invalid template '{s}': unmatched '}}' at {i}")
+                },
+                c => { literal.push(c); continue; }
+            };
+
+            if !literal.is_empty()
+            {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Variable(name));
+        }
+
+        if !literal.is_empty()
+        {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self
+        {
+            source: s.into(),
+            parts
+        })
+    }
+
+    /// names of all variables used in the template
+    pub fn variables(&self) -> impl Iterator<Item = &str>
+    {
+        self.parts.iter().filter_map(|p| match p
+        {
+            Part::Variable(name) => Some(name.as_str()),
+            Part::Literal(_) => None
+        })
+    }
+
+    /// fails if `lookup` does not know a variable used in the template
+    pub fn render<F: Fn(&str) -> Option<String>>(&self, lookup: F) -> Result<String>
+    {
+        let mut s = String::new();
+        for part in self.parts.iter()
+        {
+            match part
+            {
+                Part::Literal(literal) => s.push_str(literal),
+                Part::Variable(name) =>
+                {
+                    let Some(value) = lookup(name) else { bail!("unknown variable '{name}' in template '{}'", self.source) };
+                    s.push_str(&value);
+                }
+            }
+        }
+
+        Ok(s)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for Template
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self>
+    {
+        Self::parse(s)
+    }
+}
+
+impl Display for Template
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str(&self.source)
+    }
+}
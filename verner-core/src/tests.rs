@@ -0,0 +1,486 @@
+#[cfg(test)]
+mod test
+{
+    use std::cmp::Ordering;
+
+    use crate::{conventional::ConventionalCommit, calver::{CalDate, CalVerInc, CalVerScheme}, explain::{ExplainedHistory, Origin}, format::OutputFormat, scheme::VersionScheme, template::Template, semver::{Identifier, LabelPrecedence, SemVersion, SemVersionInc}, resolve_version, resolve_version_explained, VersionHint, VersionInc, VersionOp};
+
+    #[test]
+    fn parse_roundtrip()
+    {
+        for s in [
+            "0.0.0",
+            "1.2.3",
+            "1.2.0-alpha",
+            "1.2.0-alpha.beta.3",
+            "1.2.0-alpha.0",
+            "1.0.0-0",
+            "1.0.0-7",
+            "1.0.0-0.3.7",
+            "1.0.0-x.7.z.92",
+            "1.0.0-x-y-z.--",
+            "1.0.0-1a.02b",
+            "1.0.0+20130313144700",
+            "1.0.0-beta+exp.sha.5114f85",
+            "1.0.0+21AF26D3----117B344092BD",
+            "1.0.0-rc.1+build.001",
+        ]
+        {
+            let v = SemVersion::parse(s).unwrap_or_else(|e| panic!("{s} did not parse: {e}"));
+            assert_eq!(v.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn parse_without_patch()
+    {
+        assert_eq!(SemVersion::parse("1.2").unwrap().to_string(), "1.2.0");
+    }
+
+    #[test]
+    fn parse_build_counter()
+    {
+        let v = SemVersion::parse("1.2.0-alpha.beta.3").unwrap();
+        assert_eq!(v.label(), Some("alpha.beta"));
+        assert_eq!(v.build(), 3);
+
+        let v = SemVersion::parse("1.2.0-alpha.0").unwrap();
+        assert_eq!(v.label(), Some("alpha.0"));
+        assert_eq!(v.build(), 0);
+
+        let v = SemVersion::parse("1.2.0-4+meta").unwrap();
+        assert_eq!(v.label(), None);
+        assert_eq!(v.build(), 4);
+        assert_eq!(v.metadata(), Some("meta"));
+    }
+
+    #[test]
+    fn pre_release_identifiers()
+    {
+        let v = SemVersion::parse("1.0.0-x.7.z.92").unwrap();
+        let ids: Vec<_> = v.pre_release().collect();
+        assert_eq!(ids, [Identifier::AlphaNumeric("x"), Identifier::Numeric(7), Identifier::AlphaNumeric("z"), Identifier::Numeric(92)]);
+    }
+
+    #[test]
+    fn label_equals_build_counter()
+    {
+        let labeled = SemVersion::parse("1.0.0").unwrap().with_label(Some("rc.3".into()));
+        assert_eq!(labeled, SemVersion::parse("1.0.0-rc.3").unwrap());
+    }
+
+    #[test]
+    fn parse_invalid()
+    {
+        for s in [
+            "",
+            "1",
+            "a.b.c",
+            "01.0.0",
+            "1.00.0",
+            "1.0.01",
+            "1.0.0-",
+            "1.0.0-rc..1",
+            "1.0.0-rc.01",
+            "1.0.0-rc_1",
+            "1.0.0+",
+            "1.0.0+meta..data",
+            "1.0.0-rc+meta+data",
+            "4294967296.0.0",
+        ]
+        {
+            assert!(SemVersion::parse(s).is_err(), "{s} should not parse");
+        }
+    }
+
+    #[test]
+    fn parse_error_is_descriptive()
+    {
+        let err = SemVersion::parse("1.0.0-rc.01").unwrap_err().to_string();
+        assert!(err.contains("leading zeros"), "{err}");
+
+        let err = SemVersion::parse("1.0.0-rc..1").unwrap_err().to_string();
+        assert!(err.contains("empty identifier"), "{err}");
+    }
+
+    fn parse_all(versions: &[&str]) -> Vec<SemVersion>
+    {
+        versions.iter().map(|v| SemVersion::parse(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn precedence()
+    {
+        let ordered = parse_all(&[
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1-0",
+            "1.0.1",
+            "1.1.0",
+            "2.0.0",
+        ]);
+
+        for (i, a) in ordered.iter().enumerate()
+        {
+            for (j, b) in ordered.iter().enumerate()
+            {
+                assert_eq!(a.cmp(b), i.cmp(&j), "{a} <=> {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn precedence_ignores_metadata()
+    {
+        let a = SemVersion::parse("1.0.0+a").unwrap();
+        let b = SemVersion::parse("1.0.0+b").unwrap();
+        assert_eq!(a.cmp_precedence(&b), Ordering::Equal);
+        assert!(a < b);
+    }
+
+    #[test]
+    fn sort_and_max()
+    {
+        let mut versions = parse_all(&["1.0.0", "1.0.0-rc.1", "0.9.0", "1.0.0-rc.10", "1.0.0-beta"]);
+        versions.sort();
+        let sorted: Vec<_> = versions.iter().map(ToString::to_string).collect();
+        assert_eq!(sorted, ["0.9.0", "1.0.0-beta", "1.0.0-rc.1", "1.0.0-rc.10", "1.0.0"]);
+    }
+
+    #[test]
+    fn custom_label_precedence()
+    {
+        let precedence = LabelPrecedence::new(vec!["SNAPSHOT".into(), "alpha".into(), "beta".into(), "rc".into()]);
+        let ordered = parse_all(&[
+            "1.0.0-feat-x.3",
+            "1.0.0-SNAPSHOT.7",
+            "1.0.0-alpha",
+            "1.0.0-beta.2",
+            "1.0.0-rc",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ]);
+
+        for (i, a) in ordered.iter().enumerate()
+        {
+            for (j, b) in ordered.iter().enumerate()
+            {
+                assert_eq!(precedence.cmp(a, b), i.cmp(&j), "{a} <=> {b}");
+            }
+        }
+
+        let snapshot = SemVersion::parse("1.0.0-SNAPSHOT").unwrap();
+        let alpha = SemVersion::parse("1.0.0-alpha").unwrap();
+        assert_eq!(snapshot.cmp(&alpha), Ordering::Less);
+        assert_eq!(precedence.cmp(&alpha, &snapshot), Ordering::Greater);
+    }
+
+    #[test]
+    fn versions_are_send_and_sync()
+    {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<SemVersion>();
+        assert_send_sync::<SemVersionInc>();
+        assert_send_sync::<VersionInc<SemVersion, SemVersionInc>>();
+    }
+
+    #[test]
+    fn move_version_between_threads()
+    {
+        let version = SemVersion::parse("1.2.3-rc.1+meta").unwrap();
+        let copy = version.clone();
+        let formatted = std::thread::spawn(move || copy.to_string()).join().unwrap();
+        assert_eq!(formatted, version.to_string());
+    }
+
+    #[test]
+    fn serde_as_string()
+    {
+        let version = SemVersion::parse("1.2.0-rc.3+build.7").unwrap();
+        let yaml = serde_yaml::to_string(&version).unwrap();
+        assert_eq!(yaml.trim(), "1.2.0-rc.3+build.7");
+        assert_eq!(serde_yaml::from_str::<SemVersion>(&yaml).unwrap(), version);
+    }
+
+    #[test]
+    fn serde_rejects_invalid()
+    {
+        let err = serde_yaml::from_str::<SemVersion>("1.2.03").unwrap_err().to_string();
+        assert!(err.contains("leading zeros"), "{err}");
+    }
+
+    #[test]
+    fn caldate_from_unix()
+    {
+        assert_eq!(CalDate::from_unix(0, 0), CalDate { year: 1970, month: 1, day: 1 });
+        // 2024-02-29T23:30:00Z
+        assert_eq!(CalDate::from_unix(1709249400, 0), CalDate { year: 2024, month: 2, day: 29 });
+        assert_eq!(CalDate::from_unix(1709249400, 60), CalDate { year: 2024, month: 3, day: 1 });
+        assert_eq!(CalDate { year: 2024, month: 3, day: 1 }.ordinal(), 61);
+        assert_eq!(CalDate { year: 2023, month: 12, day: 31 }.ordinal(), 365);
+    }
+
+    #[test]
+    fn calver_format()
+    {
+        let scheme = CalVerScheme::new("YY.0W.build").unwrap();
+        let mut version = scheme.default_basis();
+        version.inc(&CalVerInc::Commit(CalDate { year: 2024, month: 1, day: 10 }));
+        assert_eq!(version.to_string(), "24.02.0");
+        version.inc(&CalVerInc::Commit(CalDate { year: 2024, month: 12, day: 31 }));
+        assert_eq!(version.to_string(), "24.53.0");
+
+        assert!(CalVerScheme::new("YYYY.0M").is_err());
+        assert!(CalVerScheme::new("patch.YYYY").is_err());
+        assert!(CalVerScheme::new("YYYY.QQ.patch").is_err());
+    }
+
+    #[test]
+    fn calver_parse_roundtrip()
+    {
+        let scheme = CalVerScheme::new("YYYY.0M.patch").unwrap();
+        for s in ["2024.05.3", "2024.11.0-rc"]
+        {
+            assert_eq!(scheme.parse(s).unwrap().to_string(), s);
+        }
+        assert!(scheme.parse("2024.05").is_err());
+        assert!(scheme.parse("2024.x.1").is_err());
+    }
+
+    #[test]
+    fn calver_counter_resets()
+    {
+        let scheme = CalVerScheme::new("YYYY.0M.patch").unwrap();
+        let mut version = scheme.parse("2024.04.2").unwrap();
+
+        version.inc(&CalVerInc::Commit(CalDate { year: 2024, month: 4, day: 30 }));
+        assert_eq!(version.to_string(), "2024.04.3");
+
+        version.inc(&CalVerInc::Commit(CalDate { year: 2024, month: 5, day: 1 }));
+        assert_eq!(version.to_string(), "2024.05.0");
+
+        version.inc(&CalVerInc::Commit(CalDate { year: 2024, month: 5, day: 2 }));
+        assert_eq!(version.to_string(), "2024.05.1");
+    }
+
+    #[test]
+    fn calver_counter_does_not_depend_on_increment_order()
+    {
+        let scheme = CalVerScheme::new("YYYY.0M.patch").unwrap();
+        let commits = [(4, 30), (5, 1), (5, 2)].map(|(month, day)| CalVerInc::Commit(CalDate { year: 2024, month, day }));
+        for incs in [commits.to_vec(), commits.iter().rev().cloned().collect()]
+        {
+            let mut version = scheme.parse("2024.04.2").unwrap();
+            incs.iter().for_each(|inc| version.inc(inc));
+            assert_eq!(version.to_string(), "2024.05.1");
+        }
+    }
+
+    fn format_all(format: OutputFormat, versions: &[(&str, &str)])
+    {
+        for (version, expected) in versions
+        {
+            let v = SemVersion::parse(version).unwrap();
+            assert_eq!(format.format(&v).unwrap(), *expected, "{format} of {version}");
+        }
+    }
+
+    #[test]
+    fn format_semver()
+    {
+        format_all(OutputFormat::Semver, &[
+            ("1.2.0", "1.2.0"),
+            ("1.2.0-rc.3", "1.2.0-rc.3"),
+            ("1.2.0-SNAPSHOT.3+meta", "1.2.0-SNAPSHOT.3+meta"),
+        ]);
+
+        let scheme = CalVerScheme::new("YYYY.0M.patch").unwrap();
+        assert_eq!(OutputFormat::Semver.format(&scheme.parse("2024.05.3").unwrap()).unwrap(), "2024.5.3");
+
+        let scheme = CalVerScheme::new("YYYY.MM.DD.build").unwrap();
+        assert_eq!(OutputFormat::Semver.format(&scheme.parse("2024.5.17.2").unwrap()).unwrap(), "2024.5.17+2");
+        assert_eq!(OutputFormat::Semver.format(&scheme.parse("2024.5.17.2-rc").unwrap()).unwrap(), "2024.5.17-rc+2");
+    }
+
+    #[test]
+    fn format_pep440()
+    {
+        format_all(OutputFormat::Pep440, &[
+            ("1.2.0", "1.2.0"),
+            ("1.2.0-rc.3", "1.2.0rc3"),
+            ("1.2.0-alpha.1", "1.2.0a1"),
+            ("1.2.0-beta", "1.2.0b0"),
+            ("1.2.0-SNAPSHOT.3", "1.2.0.dev3"),
+            ("1.2.0-4", "1.2.0.dev4"),
+            ("1.2.0-feat-Login.2", "1.2.0.dev2+feat.login"),
+            ("1.2.0-rc.1+build.5", "1.2.0rc1+build.5"),
+        ]);
+    }
+
+    #[test]
+    fn format_maven()
+    {
+        format_all(OutputFormat::Maven, &[
+            ("1.2.0", "1.2.0"),
+            ("1.2.0-SNAPSHOT.3", "1.2.0-SNAPSHOT"),
+            ("1.2.0-rc.3", "1.2.0-rc-3"),
+            ("1.2.0-rc", "1.2.0-rc"),
+            ("1.2.0-7+meta", "1.2.0-7"),
+        ]);
+    }
+
+    #[test]
+    fn format_nuget()
+    {
+        format_all(OutputFormat::Nuget, &[
+            ("1.2.0", "1.2.0"),
+            ("1.2.0-rc.3+meta", "1.2.0-rc.3+meta"),
+        ]);
+
+        let scheme = CalVerScheme::new("YYYY.MM.DD.build").unwrap();
+        assert_eq!(OutputFormat::Nuget.format(&scheme.parse("2024.5.17.2").unwrap()).unwrap(), "2024.5.17.2");
+    }
+
+    #[test]
+    fn format_debian()
+    {
+        format_all(OutputFormat::Debian, &[
+            ("1.2.0", "1.2.0"),
+            ("1.2.0-rc.3", "1.2.0~rc.3"),
+            ("1.2.0-fix-x.1+build-5", "1.2.0~fix.x.1+build.5"),
+        ]);
+    }
+
+    #[test]
+    fn format_assembly()
+    {
+        format_all(OutputFormat::Assembly, &[
+            ("1.2.0", "1.2.0.0"),
+            ("1.2.0-rc.3", "1.2.0.3"),
+        ]);
+        assert!(OutputFormat::Assembly.format(&SemVersion::parse("70000.0.0").unwrap()).is_err());
+    }
+
+    #[test]
+    fn format_from_str()
+    {
+        assert_eq!("PEP440".parse::<OutputFormat>().unwrap(), OutputFormat::Pep440);
+        assert!("npm".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn template_syntax()
+    {
+        let template = Template::parse("{major}.$minor.${patch}-$$x{{y}}").unwrap();
+        assert_eq!(template.variables().collect::<Vec<_>>(), ["major", "minor", "patch"]);
+        assert_eq!(template.render(|name| Some(name.len().to_string())).unwrap(), "5.5.5-$x{y}");
+    }
+
+    #[test]
+    fn replacement_template_syntax()
+    {
+        let template = Template::parse_replacement("{major}.$minor.${patch}-$$x}").unwrap();
+        assert_eq!(template.variables().collect::<Vec<_>>(), ["minor", "patch"]);
+        assert_eq!(template.render(|name| Some(name.len().to_string())).unwrap(), "{major}.5.5-$x}");
+    }
+
+    #[test]
+    fn template_errors()
+    {
+        assert!(Template::parse("{major").is_err());
+        assert!(Template::parse("major}").is_err());
+        assert!(Template::parse("{}").is_err());
+        assert!(Template::parse("{major}").unwrap().render(|_| None).is_err());
+    }
+
+    struct VecHistory(std::vec::IntoIter<VersionInc<SemVersion, SemVersionInc>>, usize);
+
+    impl Iterator for VecHistory
+    {
+        type Item = anyhow::Result<VersionInc<SemVersion, SemVersionInc>>;
+
+        fn next(&mut self) -> Option<Self::Item>
+        {
+            self.1 += 1;
+            self.0.next().map(Ok)
+        }
+    }
+
+    impl ExplainedHistory<SemVersion, SemVersionInc> for VecHistory
+    {
+        fn origin(&mut self) -> Origin<SemVersion, SemVersionInc>
+        {
+            Origin { node: format!("c{}", self.1), reason: "test".into(), nested: None }
+        }
+    }
+
+    #[test]
+    fn explain_records_steps_and_v_next()
+    {
+        let mut history = VecHistory(vec![
+            VersionInc::Inc(SemVersionInc::Build(1)),
+            VersionInc::Inc(SemVersionInc::Build(1)),
+            VersionInc::Fixed(SemVersion::parse("1.0.0").unwrap()),
+            VersionInc::Inc(SemVersionInc::Build(1)),
+        ].into_iter(), 0);
+
+        let explanation = resolve_version_explained(&mut history, SemVersion::default(), Some(SemVersionInc::Minor(1))).unwrap();
+        assert_eq!(explanation.version.to_string(), "1.1.0-2");
+        assert!(explanation.hint == VersionHint::Derived);
+        assert_eq!(explanation.steps.len(), 3);
+        assert_eq!(explanation.steps[2].origin.node, "c3");
+        assert!(explanation.v_next.is_some());
+        assert!(explanation.to_string().starts_with("1.1.0-2 (derived)\n  c1 test: increment by"));
+    }
+
+    #[test]
+    fn mainline_increments_apply_on_a_fixed_basis()
+    {
+        let v1_4_0 = SemVersion::parse("1.4.0").unwrap();
+        let resolve = |merges: usize|
+        {
+            let mut history = std::iter::repeat_with(|| Ok(VersionInc::Inc(SemVersionInc::Patch(1)))).take(merges)
+                .chain(std::iter::once(Ok(VersionInc::Fixed(v1_4_0.clone()))));
+            let (version, hint) = resolve_version(&mut history, SemVersion::default(), None).unwrap();
+            (version.to_string(), hint)
+        };
+
+        assert_eq!(resolve(0), ("1.4.0".to_string(), VersionHint::Fixed));
+        assert_eq!(resolve(1), ("1.4.1".to_string(), VersionHint::Derived));
+        assert_eq!(resolve(2), ("1.4.2".to_string(), VersionHint::Derived));
+    }
+
+    #[test]
+    fn increments_apply_oldest_first()
+    {
+        let resolve = |incs: Vec<SemVersionInc>|
+        {
+            let mut history = incs.into_iter().map(|inc| Ok(VersionInc::Inc(inc)))
+                .chain(std::iter::once(Ok(VersionInc::Fixed(SemVersion::parse("1.4.0").unwrap()))));
+            resolve_version(&mut history, SemVersion::default(), None).unwrap().0.to_string()
+        };
+
+        // tip first: the build after the bumps, then the newer and the older bump
+        assert_eq!(resolve(vec![SemVersionInc::Build(1), SemVersionInc::Minor(1), SemVersionInc::Patch(1)]), "1.5.0-1");
+        assert_eq!(resolve(vec![SemVersionInc::Patch(1), SemVersionInc::Minor(1)]), "1.5.1");
+        assert_eq!(resolve(vec![SemVersionInc::Minor(1), SemVersionInc::Build(1)]), "1.5.0");
+    }
+
+    #[test]
+    fn parse_conventional_commit()
+    {
+        let commit = ConventionalCommit::parse("feat(parser)!: support arrays\n\nbody").unwrap();
+        assert_eq!(commit, ConventionalCommit { r#type: "feat", scope: Some("parser"), description: "support arrays", breaking: true });
+        assert!(ConventionalCommit::parse("fix: a\n\nBREAKING CHANGE: b").unwrap().breaking);
+        assert!(!ConventionalCommit::parse("fix: a\n\nsee BREAKING CHANGE: b").unwrap().breaking);
+        assert!(ConventionalCommit::parse("Merge branch 'main'").is_none());
+        assert!(ConventionalCommit::parse("feat(parser: missing paren").is_none());
+    }
+}
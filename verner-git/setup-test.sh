@@ -19,6 +19,23 @@ function git_commit
     _git commit --allow-empty -m "$1"
 }
 
+function git_commit_at
+{
+    GIT_AUTHOR_DATE="$1" GIT_COMMITTER_DATE="$1" _git commit --allow-empty -m "$2"
+}
+
+# commits a change of the file $1 on the current branch, without a work tree
+function git_commit_file
+{
+    local blob tree parent commit
+    blob="$(echo "$2" | _git hash-object -w --stdin)"
+    _git update-index --add --cacheinfo "100644,$blob,$1"
+    tree="$(_git write-tree)"
+    parent="$(_git rev-parse -q --verify HEAD)"
+    commit="$(_git commit-tree "$tree" ${parent:+-p "$parent"} -m "$2")"
+    _git update-ref HEAD "$commit"
+}
+
 
 # begin setup releaseflow repos
 RELEASEFLOW_ROOT="$GIT_REPOS_DIR/releaseflow"
@@ -108,4 +125,385 @@ git_commit "initial commit"
 _git branch "release/1.0.x"
 _git checkout -b "feature/on-root"
 
-# end setup releaseflow repos
\ No newline at end of file
+# end setup releaseflow repos
+
+
+# begin setup calver repos
+CALVER_ROOT="$GIT_REPOS_DIR/calver"
+mkdir -p "$CALVER_ROOT"
+
+REPO="$CALVER_ROOT/2024.05.1"
+git_init
+git_commit_at "2024-04-20T12:00:00+00:00" "april release"
+_git tag v2024.04.0
+git_commit_at "2024-04-25T12:00:00+00:00" "april fix"
+git_commit_at "2024-05-02T12:00:00+00:00" "may feature"
+git_commit_at "2024-05-03T12:00:00+00:00" "may fix"
+
+# end setup calver repos
+
+
+# begin setup tag repos
+TAGS_ROOT="$GIT_REPOS_DIR/tags"
+mkdir -p "$TAGS_ROOT"
+
+REPO="$TAGS_ROOT/annotated"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git checkout -b "release/1.0.x"
+git_commit "fix the rc"
+_git tag -a v1.0.0 -m "release 1.0.0"
+git_commit "patch after release"
+
+
+REPO="$TAGS_ROOT/tag-of-tag"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git checkout -b "release/1.0.x"
+git_commit "fix the rc"
+_git tag -a candidate -m "release candidate"
+_git tag -a v1.0.0 candidate -m "promote candidate to 1.0.0"
+
+
+REPO="$TAGS_ROOT/mixed"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git checkout -b "release/1.0.x"
+git_commit "fix the rc"
+_git tag release-1.0.0
+_git tag -a v1.0.0 -m "release 1.0.0"
+git_commit "patch after release"
+
+
+REPO="$TAGS_ROOT/rc-and-release"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git checkout -b "release/1.0.x"
+git_commit "fix the rc"
+_git tag v1.0.0-rc.3
+_git tag -a v1.0.0 -m "release 1.0.0"
+
+
+REPO="$TAGS_ROOT/labels"
+git_init
+git_commit "initial commit"
+_git checkout -b "release/1.0.x"
+git_commit "pre-release"
+_git tag v1.0.0-alpha
+_git tag v1.0.0-beta
+
+
+REPO="$TAGS_ROOT/leading-zero"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit "second commit"
+_git tag v1.02.0
+
+# the merged commit is dated before the tagged commit it is based on, e.g. after a rebase
+REPO="$TAGS_ROOT/backdated-merge"
+git_init
+git_commit_at "2024-03-01T12:00:00+00:00" "initial commit"
+git_commit_at "2024-03-05T12:00:00+00:00" "release"
+_git tag v1.0.0
+_git checkout -b "backdated"
+git_commit_at "2024-03-02T12:00:00+00:00" "backdated commit"
+_git checkout main
+git_commit_at "2024-03-06T12:00:00+00:00" "commit after release"
+GIT_AUTHOR_DATE="2024-03-07T12:00:00+00:00" GIT_COMMITTER_DATE="2024-03-07T12:00:00+00:00" _git merge --no-ff --no-edit "backdated"
+
+# end setup tag repos
+
+
+# begin setup conventional commit repos
+CONVENTIONAL_ROOT="$GIT_REPOS_DIR/conventional"
+mkdir -p "$CONVENTIONAL_ROOT"
+
+REPO="$CONVENTIONAL_ROOT/1.0.1-2"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit "fix: handle empty input"
+git_commit "chore: update dependencies"
+
+
+REPO="$CONVENTIONAL_ROOT/1.1.0-3"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit "fix: handle empty input"
+git_commit "feat(api): add endpoint"
+git_commit "fix: typo"
+
+
+REPO="$CONVENTIONAL_ROOT/2.0.0-2"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit "fix: handle empty input"
+git_commit $'refactor: new config format\n\nBREAKING CHANGE: the old format is no longer supported'
+
+
+REPO="$CONVENTIONAL_ROOT/1.1.0-1"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit "docs: no bump, uses vNext"
+
+
+REPO="$CONVENTIONAL_ROOT/0.1.0-2"
+git_init
+git_commit "chore: initial commit"
+git_commit "feat: first feature"
+
+# end setup conventional commit repos
+
+
+# begin setup directive repos
+DIRECTIVES_ROOT="$GIT_REPOS_DIR/directives"
+mkdir -p "$DIRECTIVES_ROOT"
+
+REPO="$DIRECTIVES_ROOT/1.1.0-1"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit "fix something"
+git_commit "update readme [skip version]"
+
+
+REPO="$DIRECTIVES_ROOT/2.0.0-2"
+git_init
+git_commit "initial commit"
+_git tag v1.0.0
+git_commit $'remove deprecated api\n\n+semver: major'
+git_commit "fix something"
+
+
+REPO="$DIRECTIVES_ROOT/3.0.0-1"
+git_init
+git_commit "initial commit"
+git_commit $'prepare release\n\nRelease-As: 3.0.0'
+git_commit "fix something"
+
+
+REPO="$DIRECTIVES_ROOT/3.1.0-1"
+git_init
+git_commit "initial commit"
+git_commit $'prepare release\n\nRelease-As: 3.0.0'
+git_commit "feat: new feature"
+
+
+REPO="$DIRECTIVES_ROOT/unknown-increment"
+git_init
+git_commit "initial commit"
+git_commit "+semver: huge"
+
+# end setup directive repos
+
+
+# begin setup path filter repos
+PATHS_ROOT="$GIT_REPOS_DIR/paths"
+mkdir -p "$PATHS_ROOT"
+
+REPO="$PATHS_ROOT/monorepo"
+git_init
+git_commit_file "README.md" "initial commit"
+_git tag v1.0.0
+git_commit_file "api/main.rs" "api change"
+git_commit_file "web/index.html" "web change"
+git_commit_file "api/lib.rs" "api lib change"
+git_commit_file "web/style.css" "another web change"
+
+# end setup path filter repos
+
+
+# begin setup component repos
+COMPONENTS_ROOT="$GIT_REPOS_DIR/components"
+mkdir -p "$COMPONENTS_ROOT"
+
+REPO="$COMPONENTS_ROOT/monorepo"
+git_init
+git_commit_file "README.md" "initial commit"
+_git tag api/v1.0.0
+_git tag web/v2.0.0
+git_commit_file "api/main.rs" "api change"
+git_commit_file "web/index.html" "web change"
+git_commit_file "api/lib.rs" "api lib change"
+
+# end setup component repos
+
+
+# begin setup first parent repos
+FIRST_PARENT_ROOT="$GIT_REPOS_DIR/first-parent"
+mkdir -p "$FIRST_PARENT_ROOT"
+
+REPO="$FIRST_PARENT_ROOT/merged-feature"
+git_init
+git_commit_at "2024-01-01T12:00:00+00:00" "initial commit"
+_git tag v1.0.0
+_git checkout -b "feature/merged"
+git_commit_at "2024-01-02T12:00:00+00:00" "feature commit"
+_git tag v1.5.0
+git_commit_at "2024-01-03T12:00:00+00:00" "another feature commit"
+_git checkout main
+git_commit_at "2024-01-04T12:00:00+00:00" "main commit"
+_git update-ref HEAD "$(GIT_COMMITTER_DATE="2024-01-05T12:00:00+00:00" _git commit-tree "HEAD^{tree}" -p HEAD -p "feature/merged" -m "merge feature/merged")"
+
+# end setup first parent repos
+
+
+# begin setup mainline repos
+MAINLINE_ROOT="$GIT_REPOS_DIR/mainline"
+mkdir -p "$MAINLINE_ROOT"
+
+# merges the branch $1 into the current branch
+function git_merge
+{
+    _git update-ref HEAD "$(_git commit-tree "HEAD^{tree}" -p HEAD -p "$1" -m "merge $1")"
+}
+
+REPO="$MAINLINE_ROOT/merges"
+git_init
+git_commit "initial commit"
+_git tag v1.4.0
+_git checkout -b "feature/fix"
+git_commit "fix: handle empty input"
+git_commit "chore: cleanup"
+_git checkout main
+git_merge "feature/fix"
+_git branch "mainline/after-fix"
+_git checkout -b "feature/feat"
+git_commit "feat: add endpoint"
+_git checkout main
+git_merge "feature/feat"
+_git branch "mainline/after-feat"
+git_commit "docs: direct commit"
+
+REPO="$MAINLINE_ROOT/mixed"
+git_init
+git_commit "initial commit"
+_git tag v1.4.0
+git_commit "chore: direct commit"
+_git checkout -b "feature/fix"
+git_commit "fix: handle empty input"
+_git checkout main
+git_merge "feature/fix"
+_git branch "mainline/after-fix"
+git_commit "docs: direct commit"
+git_commit "fix: direct fix"
+_git branch "mainline/direct"
+_git checkout -b "feature/feat"
+git_commit "feat: add endpoint"
+_git checkout main
+git_merge "feature/feat"
+
+# end setup mainline repos
+
+
+# begin setup pull request repos
+PULL_REQUESTS_ROOT="$GIT_REPOS_DIR/pull-requests"
+mkdir -p "$PULL_REQUESTS_ROOT"
+
+REPO="$PULL_REQUESTS_ROOT/into-release"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git checkout -b "release/1.0.x"
+git_commit "fix the rc"
+_git checkout -b "pr"
+git_commit "pull request commit"
+_git update-ref "refs/merge-requests/45/head" HEAD
+_git update-ref "refs/pull/7/merge" "$(_git commit-tree "HEAD^{tree}" -p "release/1.0.x" -p HEAD -m "merge pull request 7")"
+_git checkout main
+_git branch -D "pr"
+git_commit "vNext commit"
+
+# end setup pull request repos
+
+
+# begin setup ci repos
+CI_ROOT="$GIT_REPOS_DIR/ci"
+mkdir -p "$CI_ROOT"
+
+REPO="$CI_ROOT/detached"
+git_init
+git_commit "initial commit"
+_git branch "release/1.0.x"
+_git checkout --detach
+
+# end setup ci repos
+
+
+# begin setup remote repos
+REMOTES_ROOT="$GIT_REPOS_DIR/remotes"
+mkdir -p "$REMOTES_ROOT"
+
+REPO="$REMOTES_ROOT/remote-ahead"
+git_init
+git_commit "initial commit"
+git_commit "pushed by someone else"
+_git update-ref "refs/remotes/origin/main" HEAD
+_git checkout -b "feature/remote"
+git_commit "feature commit"
+_git branch -f main HEAD~2
+
+
+REPO="$REMOTES_ROOT/diverged"
+git_init
+git_commit "initial commit"
+git_commit "pushed by someone else"
+_git update-ref "refs/remotes/origin/main" HEAD
+_git checkout -b "feature/diverged"
+git_commit "feature commit"
+_git checkout main
+_git reset --soft HEAD~1
+git_commit "local commit"
+_git checkout "feature/diverged"
+
+# end setup remote repos
+
+
+# begin setup source repos
+SOURCES_ROOT="$GIT_REPOS_DIR/sources"
+mkdir -p "$SOURCES_ROOT"
+
+REPO="$SOURCES_ROOT/nearest"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git branch "release/1.0.x"
+git_commit "vNext commit"
+_git checkout -b "release/1.1.x"
+git_commit "fix the rc"
+_git checkout -b "feature/nearest"
+git_commit "feature commit"
+_git checkout main
+git_commit "after release 1.1"
+_git checkout "feature/nearest"
+
+
+REPO="$SOURCES_ROOT/unrelated"
+git_init
+git_commit "initial commit"
+git_commit "second commit"
+_git checkout --orphan "release/9.9.x"
+git_commit "unrelated history"
+_git checkout main
+_git checkout -b "feature/unrelated"
+git_commit "feature commit"
+
+
+REPO="$SOURCES_ROOT/same-version"
+git_init
+git_commit "initial commit"
+_git checkout -b "release/1.0.x"
+git_commit "fix the rc"
+_git branch "release/1.0"
+_git checkout -b "feature/same-version"
+git_commit "feature commit"
+
+# end setup source repos
@@ -1,24 +1,102 @@
 use std::path::PathBuf;
 
-use clap::{Parser, ValueEnum};
+use anyhow::bail;
+use clap::{Parser, Subcommand, ValueEnum};
+use verner_core::{format::OutputFormat, template::Template};
 
-#[derive(Debug, Parser)]
+#[derive(Clone, Debug, Parser)]
 pub struct Args
 {
     #[arg(long = "preset", short = 'p', default_value = None)]
     pub config_preset: Option<ConfigPreset>,
 
+    /// only use local branches as sources and tracked branches
     #[arg(long = "local", default_value_t = false)]
     pub use_local: bool,
 
+    /// only use branches of the tracked remotes as sources and tracked branches
+    #[arg(long = "remote", default_value_t = false, conflicts_with = "use_local")]
+    pub use_remote: bool,
+
     #[arg(long = "use-ref", default_value = None)]
     pub use_ref: Option<String>,
 
     #[arg(long = "on-branch", default_value = None)]
     pub override_branch_name: Option<String>,
 
+    /// the branch a pull request merges into, detected from the first parent of `pull/<number>/merge` refs if not set
+    #[arg(long = "pr-target", default_value = None)]
+    pub pr_target: Option<String>,
+
     #[arg(long = "git-dir", default_value = None)]
-    pub git_dir: Option<PathBuf>
+    pub git_dir: Option<PathBuf>,
+
+    /// output format: semver, pep440, maven, nuget, debian or assembly
+    #[arg(long = "format", default_value = None)]
+    pub format: Option<OutputFormat>,
+
+    /// output template, e.g. `{major}.{minor}.{patch}+{sha_short}`
+    #[arg(long = "template", default_value = None, conflicts_with = "format")]
+    pub template: Option<Template>,
+
+    /// text (default) prints the version, json prints all version components, formats and how the version was solved
+    #[arg(long = "output", value_enum, default_value = None)]
+    pub output: Option<OutputMode>,
+
+    /// only count commits touching these paths, overrides the paths of the config
+    #[arg(long = "include-path")]
+    pub include_paths: Vec<String>,
+
+    /// ignore changes of these paths, overrides the paths of the config
+    #[arg(long = "exclude-path")]
+    pub exclude_paths: Vec<String>,
+
+    /// solve the version of a component of the config
+    #[arg(long = "component", default_value = None)]
+    pub component: Option<String>,
+
+    /// solve the versions of all components of the config
+    #[arg(long = "all-components", default_value_t = false, conflicts_with = "component")]
+    pub all_components: bool,
+
+    #[command(subcommand)]
+    pub command: Option<GitCommand>
+}
+
+impl Args
+{
+    pub fn output_mode(&self) -> OutputMode
+    {
+        self.output.unwrap_or(OutputMode::Text)
+    }
+
+    /// `--format`, `--template` and `explain` only apply to text output, clap cannot express conflicts with a single value
+    pub fn check_output(&self) -> anyhow::Result<()>
+    {
+        if self.output_mode() == OutputMode::Json && (self.format.is_some() || self.template.is_some())
+        {
+            bail!("--output json cannot be used with --format or --template");
+        }
+        if self.output_mode() == OutputMode::Json && self.command == Some(GitCommand::Explain)
+        {
+            bail!("--output json cannot be used with explain");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq)]
+pub enum GitCommand
+{
+    /// show how every commit from the tip to the basis contributed to the version
+    Explain
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode
+{
+    Text,
+    Json
 }
 
 #[derive(ValueEnum, Clone, Debug)]
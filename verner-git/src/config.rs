@@ -1,14 +1,14 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fmt::Display, path::Path};
 
-use anyhow::bail;
-use git2::Oid;
-use regex::Regex;
+use anyhow::{bail, Context};
+use git2::{Oid, Pathspec, PathspecFlags};
+use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
-use verner_core::semver::{SemVersion, SemVersionInc};
+use verner_core::{conventional::ConventionalCommit, scheme::{ConfigInc, VersionScheme}, semver::{LabelPrecedence, SemVerScheme}, template::Template};
 
 use crate::cli::ConfigPreset;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RawBranchConfig
 {
     /// regex that matches branch short names (excluding origin)
@@ -35,79 +35,625 @@ pub struct RawBranchConfig
     pub sources: Vec<String>,
     
     /// vNext rule for the current branch - what is incremented after sovling the base version?
+    /// the value is an increment of the configured version scheme
     #[serde(default)]
     #[serde(skip_serializing_if="Option::is_none")]
-    pub v_next: Option<SemVersionInc>,
+    pub v_next: Option<ConfigInc>,
 
     /// max soving depth
     #[serde(default)]
     #[serde(skip_serializing_if="Option::is_none")]
-    pub max_depth: Option<u32>
+    pub max_depth: Option<u32>,
+
+    /// follow only the first parent of merge commits, so a merge counts as one commit
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub first_parent: Option<bool>,
+
+    /// mainline strategy: every merge of the first-parent chain increments the version by this instead of the build counter,
+    /// or by the highest increment requested by the merged commits, direct commits increment the build counter
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub mainline: Option<ConfigInc>,
+
+    /// branch type referenced by `sources` and `tracked`, defaults to the name of the configuration
+    /// several configurations may share one type
+    #[serde(default)]
+    #[serde(rename = "type")]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub r#type: Option<String>,
+
+    /// if a branch matches several configurations, the one with the highest priority is used
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub priority: Option<i32>,
+
+    /// derive the increment from conventional commit messages instead of vNext
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub conventional_commits: Option<RawConventionalCommits>,
+
+    /// recognize directives in commit messages that force increments, skip commits or set the version
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub directives: Option<RawDirectives>
+}
+
+/// regex patterns of commit message directives, a pattern set to `null` is disabled
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RawDirectives
+{
+    /// forces an increment, the group `inc` names a key of `increments` or `none`
+    #[serde(default = "default_bump_directive")]
+    pub bump: Option<String>,
+
+    /// the commit does not change the version
+    #[serde(default = "default_skip_directive")]
+    pub skip: Option<String>,
+
+    /// the commit has the version in the group `version`
+    #[serde(default = "default_release_as_directive")]
+    pub release_as: Option<String>,
+
+    /// increments by name, defaults to the scheme's `major`, `minor` and `patch`
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub increments: Option<HashMap<String, ConfigInc>>
+}
+
+fn default_bump_directive() -> Option<String> { Some(r"\+semver:\s*(?<inc>\w+)".into()) }
+fn default_skip_directive() -> Option<String> { Some(r"\[skip version\]".into()) }
+fn default_release_as_directive() -> Option<String> { Some(r"(?m)^Release-As:\s*(?<version>\S+)\s*$".into()) }
+
+/// compiles a directive pattern that has to contain the capture group `group`
+fn directive_regex(pattern: &Option<String>, name: &str, group: Option<&str>) -> anyhow::Result<Option<Regex>>
+{
+    let Some(pattern) = pattern else { return Ok(None) };
+    let regex = Regex::new(pattern).with_context(|| format!("invalid {name} directive"))?;
+    if let Some(group) = group.filter(|group| !regex.capture_names().flatten().any(|n| n == *group))
+    {
+        bail!("{name} directive '{pattern}' has no capture group '{group}'");
+    }
+    Ok(Some(regex))
+}
+
+impl RawDirectives
+{
+    /// compiles the patterns, `increments` are required if the scheme has no defaults
+    fn parse<S: VersionScheme>(&self, scheme: &S) -> anyhow::Result<Directives<S::Inc>>
+    {
+        let increments = match (&self.increments, scheme.change_incs())
+        {
+            (Some(increments), _) => increments.iter()
+                .map(|(name, inc)| Ok((name.to_ascii_lowercase(), scheme.config_inc(*inc).with_context(|| format!("invalid increment {name}"))?)))
+                .collect::<anyhow::Result<_>>()?,
+            (None, Some(defaults)) => HashMap::from([("major".into(), defaults.breaking), ("minor".into(), defaults.feature), ("patch".into(), defaults.fix)]),
+            (None, None) => bail!("the version scheme has no default increments for directives, set `increments`")
+        };
+        Ok(Directives
+        {
+            bump: directive_regex(&self.bump, "bump", Some("inc"))?,
+            skip: directive_regex(&self.skip, "skip", None)?,
+            release_as: directive_regex(&self.release_as, "release_as", Some("version"))?,
+            increments
+        })
+    }
+}
+
+pub struct Directives<Inc>
+{
+    bump: Option<Regex>,
+    skip: Option<Regex>,
+    release_as: Option<Regex>,
+    increments: HashMap<String, Inc>
+}
+
+/// a directive found in a commit message
+pub enum Directive<'a, Inc>
+{
+    Skip,
+    ReleaseAs(&'a str),
+
+    /// `None` if the directive explicitly requests no increment
+    Bump(Option<&'a Inc>)
+}
+
+impl<Inc> Directives<Inc>
+{
+    /// the first directive in `message`, skip takes precedence over release_as, which takes precedence over bump
+    pub fn find<'a>(&'a self, message: &'a str) -> anyhow::Result<Option<Directive<'a, Inc>>>
+    {
+        if self.skip.as_ref().is_some_and(|skip| skip.is_match(message))
+        {
+            return Ok(Some(Directive::Skip));
+        }
+
+        if let Some(captures) = self.release_as.as_ref().and_then(|release_as| release_as.captures(message))
+        {
+            let Some(version) = captures.name("version") else { bail!("release_as directive matched '{}' without the group 'version'", &captures[0]) };
+            return Ok(Some(Directive::ReleaseAs(version.as_str())));
+        }
+
+        let Some(captures) = self.bump.as_ref().and_then(|bump| bump.captures(message)) else { return Ok(None) };
+        let Some(name) = captures.name("inc").map(|inc| inc.as_str().to_ascii_lowercase()) else { return Ok(None) };
+        if name == "none"
+        {
+            return Ok(Some(Directive::Bump(None)));
+        }
+
+        let Some(inc) = self.increments.get(&name) else
+        {
+            let mut known: Vec<&str> = self.increments.keys().map(String::as_str).collect();
+            known.sort();
+            bail!("unknown increment '{name}' in bump directive, expected one of: none, {}", known.join(", "));
+        };
+        Ok(Some(Directive::Bump(Some(inc))))
+    }
+}
+
+/// increments of conventional commits
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RawConventionalCommits
+{
+    /// increment of commits marked as breaking change, defaults to the scheme's `major`
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub breaking: Option<ConfigInc>,
+
+    /// increments by commit type, types without increment only count as commit, defaults to `feat` and `fix`
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub types: Option<HashMap<String, ConfigInc>>
+}
+
+impl RawConventionalCommits
+{
+    /// `breaking` and `types` are required if the scheme has no defaults
+    fn parse<S: VersionScheme>(&self, scheme: &S) -> anyhow::Result<ConventionalCommits<S::Inc>>
+    {
+        let defaults = scheme.change_incs();
+        let breaking = match (&self.breaking, &defaults)
+        {
+            (Some(breaking), _) => scheme.config_inc(*breaking).context("invalid increment for breaking changes")?,
+            (None, Some(defaults)) => defaults.breaking.clone(),
+            (None, None) => bail!("the version scheme has no default increment for breaking changes, set `breaking`")
+        };
+        let types = match (&self.types, defaults)
+        {
+            (Some(types), _) => types.iter()
+                .map(|(r#type, inc)| Ok((r#type.to_ascii_lowercase(), scheme.config_inc(*inc).with_context(|| format!("invalid increment for commit type {type}"))?)))
+                .collect::<anyhow::Result<_>>()?,
+            (None, Some(defaults)) => HashMap::from([("feat".into(), defaults.feature), ("fix".into(), defaults.fix)]),
+            (None, None) => bail!("the version scheme has no default increments for commit types, set `types`")
+        };
+        Ok(ConventionalCommits { breaking, types })
+    }
+}
+
+pub struct ConventionalCommits<Inc>
+{
+    breaking: Inc,
+    types: HashMap<String, Inc>
+}
+
+impl<Inc> ConventionalCommits<Inc>
+{
+    /// the increment requested by a commit message, `None` for other commit types and non-conventional messages
+    pub fn inc_for(&self, message: &str) -> Option<&Inc>
+    {
+        let commit = ConventionalCommit::parse(message)?;
+        if commit.breaking
+        {
+            return Some(&self.breaking);
+        }
+        self.types.get(&commit.r#type.to_ascii_lowercase())
+    }
 }
 impl RawBranchConfig {
-    pub fn parse(self, r#type: String) -> anyhow::Result<BranchConfig> {
+    pub fn parse<S: VersionScheme>(self, name: String, scheme: S) -> anyhow::Result<BranchConfig<S>> {
+        let regex = Regex::new(&self.regex)?;
+        let label = self.label.as_deref().map(|label| parse_template(label, &regex, &BRANCH_VARIABLES))
+            .transpose().with_context(|| format!("invalid label of branch config {name}"))?;
+
+        let base_version = self.base_version.as_deref().map(|base_version| parse_template(base_version, &regex, &BRANCH_VARIABLES))
+            .transpose().with_context(|| format!("invalid base_version of branch config {name}"))?;
+
+        if let Some(ref base_version) = base_version
+        {
+            validate_version_template(&scheme, base_version).with_context(|| format!("invalid base_version of branch config {name}"))?;
+        }
+
+        let v_next = self.v_next.map(|inc| scheme.config_inc(inc)).transpose().with_context(|| format!("invalid v_next of branch config {name}"))?;
+        let mainline = self.mainline.map(|inc| scheme.config_inc(inc)).transpose().with_context(|| format!("invalid mainline of branch config {name}"))?;
+        let conventional_commits = self.conventional_commits.as_ref().map(|cc| cc.parse(&scheme)).transpose().with_context(|| format!("invalid conventional_commits of branch config {name}"))?;
+        let directives = self.directives.as_ref().map(|directives| directives.parse(&scheme)).transpose().with_context(|| format!("invalid directives of branch config {name}"))?;
+
         Ok(
             BranchConfig
             {
-                r#type,
-                regex: Regex::new(&self.regex)?,
+                r#type: self.r#type.clone().unwrap_or_else(|| name.clone()),
+                name,
+                regex,
+                label,
+                base_version,
+                v_next,
+                mainline,
+                conventional_commits,
+                directives,
+                scheme,
                 raw: self
             }
         )
     }
 }
 
+/// fails if branch types source each other in a cycle, solving them would recurse until `max_depth`,
+/// or if they track each other in a cycle
+///
+/// a tracked branch only provides the base version of its root, so a branch may track a branch it is the source of
+/// (e.g. `main` tracks `release`)
+fn check_source_cycles<S: VersionScheme>(branches: &[BranchConfig<S>]) -> anyhow::Result<()>
+{
+    let mut sources: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut tracked: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for branch in branches.iter()
+    {
+        sources.entry(branch.r#type()).or_default().extend(branch.raw.sources.iter().map(String::as_str));
+        tracked.entry(branch.r#type()).or_default().extend(branch.raw.tracked.iter().map(String::as_str));
+    }
+
+    fn visit<'a>(r#type: &'a str, edges: &BTreeMap<&'a str, BTreeSet<&'a str>>, path: &mut Vec<&'a str>, done: &mut BTreeSet<&'a str>) -> Result<(), String>
+    {
+        if let Some(start) = path.iter().position(|t| *t == r#type)
+        {
+            return Err(path[start..].iter().chain(std::iter::once(&r#type)).copied().collect::<Vec<_>>().join(" -> "));
+        }
+        if !done.insert(r#type)
+        {
+            return Ok(());
+        }
+
+        path.push(r#type);
+        for next in edges.get(r#type).into_iter().flatten()
+        {
+            visit(next, edges, path, done)?;
+        }
+        path.pop();
+        Ok(())
+    }
+
+    for (edges, kind) in [(&sources, "branch sources"), (&tracked, "tracked branches")]
+    {
+        let mut done = BTreeSet::new();
+        for r#type in edges.keys()
+        {
+            if let Err(cycle) = visit(r#type, edges, &mut Vec::new(), &mut done)
+            {
+                bail!("{kind} form a cycle: {cycle}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// fails if two configs with the same priority have the same regex, every branch they match would be ambiguous
+fn check_overlapping_branches<S: VersionScheme>(branches: &[BranchConfig<S>]) -> anyhow::Result<()>
+{
+    for (i, a) in branches.iter().enumerate()
+    {
+        if let Some(b) = branches[i + 1..].iter().find(|b| b.priority() == a.priority() && b.regex.as_str() == a.regex.as_str())
+        {
+            bail!("branch configs {} and {} have the same regex and priority {}, set a priority to choose one", a.name(), b.name(), a.priority());
+        }
+    }
+    Ok(())
+}
+
+/// fails if several of `configs` (ordered by descending priority) with the highest matching priority match `short_name`,
+/// since the result would be arbitrary
+fn check_ambiguous_match<'a, S: VersionScheme + 'a>(configs: impl Iterator<Item = &'a BranchConfig<S>>, short_name: &str) -> anyhow::Result<()>
+{
+    let matching: Vec<&BranchConfig<S>> = configs.filter(|c| c.regex.is_match(short_name)).collect();
+    let Some(best) = matching.first() else { return Ok(()) };
+
+    let ambiguous: Vec<&str> = matching.iter().filter(|c| c.priority() == best.priority()).map(|c| c.name()).collect();
+    if ambiguous.len() > 1
+    {
+        bail!("branch {short_name} matches several branch configs with priority {}: {}, set a priority to choose one", best.priority(), ambiguous.join(", "));
+    }
+    Ok(())
+}
+
+/// matches `short_name` against all `configs` (ordered by descending priority), see `check_ambiguous_match`
+fn best_branch_match<'a, S: VersionScheme>(configs: impl Iterator<Item = &'a BranchConfig<S>> + Clone, short_name: &str, id: Oid, pr_target: Option<&str>) -> anyhow::Result<Option<BranchMatch<'a, S>>>
+{
+    check_ambiguous_match(configs.clone(), short_name)?;
+    let Some(best) = configs.into_iter().find(|c| c.regex.is_match(short_name)) else { return Ok(None) };
+    best.try_match_with_pr_target(short_name, id, pr_target)
+}
+
+/// GitHub Actions, Azure Pipelines, GitLab CI and Jenkins
+const DEFAULT_CI_BRANCH_VARIABLES: [&str; 5] = ["GITHUB_REF", "GITHUB_HEAD_REF", "BUILD_SOURCEBRANCH", "CI_COMMIT_REF_NAME", "BRANCH_NAME"];
+
+/// variables of branch label and base_version templates, in addition to the regex captures
+const BRANCH_VARIABLES: [&str; 8] = ["sha", "sha_short", "hash", "hash_short", "branch", "branch_type", "pr", "target_branch"];
+
+/// variables of tag version templates, in addition to the regex captures
+const TAG_VARIABLES: [&str; 5] = ["sha", "sha_short", "hash", "hash_short", "tag"];
+
+/// number of a pull request ref, e.g. `pull/123/merge` or `merge-requests/45/head`
+pub fn pull_request_number(short_name: &str) -> Option<&str>
+{
+    let rest = short_name.strip_prefix("pull/").or_else(|| short_name.strip_prefix("merge-requests/"))?;
+    let (number, kind) = rest.split_once('/')?;
+    (!number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) && matches!(kind, "merge" | "head")).then_some(number)
+}
+
+fn is_capture(regex: &Regex, name: &str) -> bool
+{
+    match name.parse::<usize>()
+    {
+        Ok(i) => i < regex.captures_len(),
+        Err(_) => regex.capture_names().flatten().any(|n| n == name)
+    }
+}
+
+/// value of a capture group, groups that did not participate in the match are empty
+fn capture(regex: &Regex, captures: &Captures, name: &str) -> Option<String>
+{
+    if !is_capture(regex, name)
+    {
+        return None;
+    }
+
+    let m = match name.parse::<usize>()
+    {
+        Ok(i) => captures.get(i),
+        Err(_) => captures.name(name)
+    };
+    Some(m.map_or("", |m| m.as_str()).into())
+}
+
+fn commit_variable(id: Oid, name: &str) -> Option<String>
+{
+    match name
+    {
+        "sha" | "hash" => Some(id.to_string()),
+        "sha_short" | "hash_short" => Some(id.to_string()[..8].into()),
+        _ => None
+    }
+}
+
+/// parses a template that may only use the capture groups of `regex` and `variables`
+fn parse_template(template: &str, regex: &Regex, variables: &[&str]) -> anyhow::Result<Template>
+{
+    let template = Template::parse_replacement(template)?;
+    if let Some(unknown) = template.variables().find(|v| !variables.contains(v) && !is_capture(regex, v))
+    {
+        bail!("unknown variable '{unknown}' in template '{template}'");
+    }
+    Ok(template)
+}
+
+/// checks that `template` expands to a valid version by substituting all variables with a placeholder
+///
+/// a template that only consists of a single variable can only be checked when matching
+fn validate_version_template<S: VersionScheme>(scheme: &S, template: &Template) -> anyhow::Result<()>
+{
+    let expanded = template.render(|_| Some("0".into()))?;
+    if expanded == "0" && template.source() != "0"
+    {
+        return Ok(());
+    }
+
+    scheme.parse(&expanded)?;
+    Ok(())
+}
+
+/// the version scheme of a config file
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RawScheme
+{
+    #[default]
+    Semver,
+
+    /// calendar versioning with the given format, e.g. `!calver YYYY.0M.patch`
+    Calver(String)
+}
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RawConfig
 {
+    /// version scheme used for all versions in this config, defaults to `semver`
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub scheme: Option<RawScheme>,
+
     /// list of all remotes to consider
     #[serde(default)]
     #[serde(skip_serializing_if="Vec::is_empty")]
     pub tracked_remotes: Vec<String>,
 
+    /// environment variables holding the branch name in CI, used in this order if HEAD is detached,
+    /// defaults to the variables of common CI systems
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub ci_branch_variables: Option<Vec<String>>,
+
     /// list of tags to parse
     #[serde(default)]
     #[serde(skip_serializing_if="HashMap::is_empty")]
     pub tags: HashMap<String, RawTagConfig>,
+
+    /// precedence of pre-release labels from lowest to highest, e.g. `[alpha, beta, rc]`
+    #[serde(default)]
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    pub label_precedence: Vec<String>,
+
+    /// only commits touching these paths change the version
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub paths: Option<RawPathFilter>,
     
     /// branch configurations
+    pub branches: HashMap<String, RawBranchConfig>,
+
+    /// independently versioned components of the repository, e.g. in a monorepo
+    #[serde(default)]
+    #[serde(skip_serializing_if="HashMap::is_empty")]
+    pub components: HashMap<String, RawComponent>
+}
+
+/// overrides of the config for one component
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RawComponent
+{
+    /// tags of the component, e.g. `api/v1.4.0`, replace the tags of the config
+    #[serde(default)]
+    #[serde(skip_serializing_if="HashMap::is_empty")]
+    pub tags: HashMap<String, RawTagConfig>,
+
+    /// replace the paths of the config
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub paths: Option<RawPathFilter>,
+
+    /// replace the branch configurations of the same name
+    #[serde(default)]
+    #[serde(skip_serializing_if="HashMap::is_empty")]
     pub branches: HashMap<String, RawBranchConfig>
 }
 
+/// git pathspecs, e.g. `services/api` or `*.rs`
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RawPathFilter
+{
+    /// a commit has to touch one of these paths, all paths if empty
+    #[serde(default)]
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    pub include: Vec<String>,
+
+    /// changes of these paths are ignored
+    #[serde(default)]
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    pub exclude: Vec<String>
+}
+
+impl RawPathFilter
+{
+    fn parse(&self) -> anyhow::Result<PathFilter>
+    {
+        let pathspec = |paths: &Vec<String>| (!paths.is_empty()).then(|| Pathspec::new(paths.iter())).transpose();
+        Ok(PathFilter
+        {
+            include: pathspec(&self.include).context("invalid include path")?,
+            exclude: pathspec(&self.exclude).context("invalid exclude path")?
+        })
+    }
+}
+
+pub struct PathFilter
+{
+    include: Option<Pathspec>,
+    exclude: Option<Pathspec>
+}
+
+impl PathFilter
+{
+    /// whether a changed path counts for the version
+    pub fn matches(&self, path: &Path) -> bool
+    {
+        self.include.as_ref().is_none_or(|include| include.matches_path(path, PathspecFlags::DEFAULT))
+            && !self.exclude.as_ref().is_some_and(|exclude| exclude.matches_path(path, PathspecFlags::DEFAULT))
+    }
+}
+
 impl RawConfig
 {
-    pub fn parse(self) -> anyhow::Result<Config>
+    /// the SemVer scheme with the configured label precedence
+    pub fn semver_scheme(&self) -> SemVerScheme
+    {
+        SemVerScheme::new(LabelPrecedence::new(self.label_precedence.clone()))
+    }
+
+    /// the config of a component, the component's settings override the shared ones
+    pub fn for_component(&self, name: &str) -> anyhow::Result<RawConfig>
+    {
+        let Some(component) = self.components.get(name) else
+        {
+            let mut known: Vec<&str> = self.components.keys().map(String::as_str).collect();
+            known.sort();
+            bail!("unknown component '{name}', expected one of: {}", known.join(", "));
+        };
+
+        let mut config = self.clone();
+        config.components.clear();
+        if !component.tags.is_empty()
+        {
+            config.tags = component.tags.clone();
+        }
+        if component.paths.is_some()
+        {
+            config.paths = component.paths.clone();
+        }
+        config.branches.extend(component.branches.clone());
+
+        Ok(config)
+    }
+
+    pub fn parse<S: VersionScheme + Clone>(self, scheme: S) -> anyhow::Result<Config<S>>
     {
+        let mut branches = self.branches.into_iter().map(|e| e.1.parse(e.0, scheme.clone())).collect::<anyhow::Result<Vec<BranchConfig<S>>>>()?;
+        // highest priority first, the name keeps the order stable
+        branches.sort_by(|a, b| b.priority().cmp(&a.priority()).then_with(|| a.name.cmp(&b.name)));
+        check_overlapping_branches(&branches)?;
+        check_source_cycles(&branches)?;
+        let mut tags = self.tags.into_iter().map(|(k, v)| v.parse(&k, scheme.clone())).collect::<anyhow::Result<Vec<TagConfig<S>>>>()?;
+        // a tag uses the first matching config, so the same order as for branches
+        tags.sort_by(|a, b| b.priority().cmp(&a.priority()).then_with(|| a.r#type.cmp(&b.r#type)));
+
         Ok(
             Config
             {
                 tracked_remotes: self.tracked_remotes,
-                tags: self.tags.into_iter().map(|(k, v)| v.parse(&k)).collect::<anyhow::Result<Vec<TagConfig>>>()?,
-                branches: self.branches.into_iter().map(|e| e.1.parse(e.0)).collect::<anyhow::Result<Vec<BranchConfig>>>()?,
+                ci_branch_variables: self.ci_branch_variables.unwrap_or_else(|| DEFAULT_CI_BRANCH_VARIABLES.map(String::from).to_vec()),
+                paths: self.paths.as_ref().map(RawPathFilter::parse).transpose()?,
+                tags,
+                branches,
+                scheme,
             }
         )
     }
 }
 
-impl TryInto<Config> for RawConfig
+impl TryInto<Config<SemVerScheme>> for RawConfig
 {
     type Error = anyhow::Error;
 
-    fn try_into(self) -> std::prelude::v1::Result<Config, Self::Error>
+    fn try_into(self) -> std::prelude::v1::Result<Config<SemVerScheme>, Self::Error>
     {
-        self.parse()
+        let scheme = self.semver_scheme();
+        self.parse(scheme)
     }
 }
 
-pub struct BranchConfig
+pub struct BranchConfig<S: VersionScheme>
 {
+    name: String,
     r#type: String,
     raw: RawBranchConfig,
     regex: Regex,
+    label: Option<Template>,
+    base_version: Option<Template>,
+    v_next: Option<S::Inc>,
+    mainline: Option<S::Inc>,
+    conventional_commits: Option<ConventionalCommits<S::Inc>>,
+    directives: Option<Directives<S::Inc>>,
+    scheme: S
 }
 
-impl BranchConfig {
+impl<S: VersionScheme> BranchConfig<S> {
     pub fn raw(&self) -> &RawBranchConfig {
         &self.raw
     }
@@ -115,13 +661,36 @@ impl BranchConfig {
     pub fn regex(&self) -> &Regex {
         &self.regex
     }
+
+    pub fn v_next(&self) -> Option<&S::Inc> {
+        self.v_next.as_ref()
+    }
+
+    /// increment of every merge with the mainline strategy
+    pub fn mainline(&self) -> Option<&S::Inc> {
+        self.mainline.as_ref()
+    }
+
+    pub fn conventional_commits(&self) -> Option<&ConventionalCommits<S::Inc>> {
+        self.conventional_commits.as_ref()
+    }
+
+    pub fn directives(&self) -> Option<&Directives<S::Inc>> {
+        self.directives.as_ref()
+    }
     
 
-    pub fn try_match<'a>(&'a self, short_name: &str, tip: Oid) -> anyhow::Result<Option<BranchMatch<'a>>>
+    pub fn try_match<'a>(&'a self, short_name: &str, tip: Oid) -> anyhow::Result<Option<BranchMatch<'a, S>>>
+    {
+        self.try_match_with_pr_target(short_name, tip, None)
+    }
+
+    /// like `try_match`, `pr_target` is the branch a pull request merges into
+    pub fn try_match_with_pr_target<'a>(&'a self, short_name: &str, tip: Oid, pr_target: Option<&str>) -> anyhow::Result<Option<BranchMatch<'a, S>>>
     {
         if let Some(captures) = self.regex().captures(short_name)
         {
-            return Ok(Some(BranchMatch::create(tip, captures, &self)?));
+            return Ok(Some(BranchMatch::create(tip, short_name, captures, self, pr_target)?));
         }
 
         Ok(None)
@@ -130,57 +699,88 @@ impl BranchConfig {
     pub fn r#type(&self) -> &str {
         &self.r#type
     }
+
+    /// name of the configuration in `branches`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.raw.priority.unwrap_or_default()
+    }
 }
-pub struct Config
+pub struct Config<S: VersionScheme>
 {
+    pub scheme: S,
     pub tracked_remotes: Vec<String>,
-    pub tags: Vec<TagConfig>,
-    pub branches: Vec<BranchConfig>
+    pub ci_branch_variables: Vec<String>,
+    pub paths: Option<PathFilter>,
+    pub tags: Vec<TagConfig<S>>,
+    pub branches: Vec<BranchConfig<S>>
 }
 
-#[derive(Clone)]
-pub struct BranchMatch<'a>
+pub struct BranchMatch<'a, S: VersionScheme>
 {
-    config: &'a BranchConfig,
+    config: &'a BranchConfig<S>,
+    name: String,
     tag: Option<String>,
     tip: Oid,
-    base_version: Option<SemVersion>
+    base_version: Option<S::Version>,
+    pr_target: Option<String>
 }
-impl<'a> BranchMatch<'a> {
-    fn create(tip: Oid, captures: regex::Captures<'_>, config: &'a BranchConfig) -> anyhow::Result<Self>
+
+impl<S: VersionScheme> Clone for BranchMatch<'_, S>
+{
+    fn clone(&self) -> Self
     {
-        let tag = if let Some(ref label_template) = config.raw.label
+        Self
         {
-            let tip_hash = tip.to_string();
-            let mut t = String::new();
-            let tpl = label_template.replace("$hash_short", &tip_hash[..8])
-                                            .replace("$hash", &tip_hash);
-            
-            captures.expand(&tpl, &mut t);
-            Some(t)
+            config: self.config,
+            name: self.name.clone(),
+            tag: self.tag.clone(),
+            tip: self.tip,
+            base_version: self.base_version.clone(),
+            pr_target: self.pr_target.clone()
         }
-        else { None };
+    }
+}
+
+impl<'a, S: VersionScheme> BranchMatch<'a, S> {
+    fn create(tip: Oid, short_name: &str, captures: Captures<'_>, config: &'a BranchConfig<S>, pr_target: Option<&str>) -> anyhow::Result<Self>
+    {
+        let variables = |name: &str| commit_variable(tip, name)
+            .or_else(|| match name
+            {
+                "branch" => Some(short_name.into()),
+                "branch_type" => Some(config.r#type.clone()),
+                // empty unless the branch is a pull request
+                "pr" => Some(pull_request_number(short_name).unwrap_or_default().into()),
+                "target_branch" => Some(pr_target.unwrap_or_default().into()),
+                _ => None
+            })
+            .or_else(|| capture(&config.regex, &captures, name));
+
+        let tag = config.label.as_ref().map(|label| label.render(variables)).transpose()?;
         
-        let mut base_version = if let Some(ref template) = config.raw().base_version
+        let mut base_version = if let Some(ref template) = config.base_version
         {
-            let mut base_version_str = String::new();
-            captures.expand(&template, &mut base_version_str);
-            let Some(parsed) = SemVersion::parse(&base_version_str) else { bail!("'{base_version_str}' is an invalid version string"); };
-            Some(parsed)
+            Some(config.scheme.parse(&template.render(variables)?)?)
         }
         else { None };
         
         if let Some(ref tag) = tag
         {
-            base_version = base_version.map(|v|v.with_label(Some(tag.into())));
+            base_version = base_version.map(|v|config.scheme.with_label(&v, Some(tag)));
         }
 
         Ok(Self
         {
             tip,
             config,
+            name: short_name.into(),
             tag,
-            base_version
+            base_version,
+            pr_target: pr_target.map(str::to_string)
         })
     }
     
@@ -189,59 +789,66 @@ impl<'a> BranchMatch<'a> {
         self.tip
     }
     
-    pub fn config(&self) -> &BranchConfig {
+    pub fn config(&self) -> &'a BranchConfig<S> {
         self.config
     }
+
+    /// short name of the matched branch
+    pub fn name(&self) -> &str {
+        &self.name
+    }
     
     pub fn tag(&self) -> Option<&str> {
-        self.tag.as_ref().map(|x| x.as_str())
+        self.tag.as_deref()
     }
     
-    pub fn base_version(&self) -> Option<&SemVersion> {
+    pub fn base_version(&self) -> Option<&S::Version> {
         self.base_version.as_ref()
     }
+
+    /// the branch a pull request merges into, it is the only source of the pull request
+    pub fn pr_target(&self) -> Option<&str> {
+        self.pr_target.as_deref()
+    }
 }
 
-impl Config
+impl<S: VersionScheme> Config<S>
 {
-    pub fn find_branches<'a>(&'a self, r#type: &'a str) -> impl Iterator<Item = &'a BranchConfig> + 'a
+    pub fn find_branches<'a>(&'a self, r#type: &'a str) -> impl Iterator<Item = &'a BranchConfig<S>> + 'a
     {
         self.branches.iter().filter(move |e| e.r#type == r#type)
     }
 
-    pub fn try_match_branch<'a>(&'a self, short_name: &str, id: Oid) -> anyhow::Result<Option<BranchMatch<'a>>>
+    pub fn try_match_branch<'a>(&'a self, short_name: &str, id: Oid) -> anyhow::Result<Option<BranchMatch<'a, S>>>
     {
-        for c in self.branches.iter()
-        {
-            
-            if let Some(m) = c.try_match(short_name, id)?
-            {
-                return Ok(Some(m));
-            }
-        }
+        best_branch_match(self.branches.iter(), short_name, id, None)
+    }
 
-        Ok(None)
+    /// like `try_match_branch`, `pr_target` is the branch a pull request merges into
+    pub fn try_match_branch_with_pr_target<'a>(&'a self, short_name: &str, id: Oid, pr_target: Option<&str>) -> anyhow::Result<Option<BranchMatch<'a, S>>>
+    {
+        best_branch_match(self.branches.iter(), short_name, id, pr_target)
     }
 
-    pub fn find_type_branch_config_for<'a>(&'a self, short_name: &str, id: Oid, r#type: &str) -> anyhow::Result<Option<BranchMatch<'a>>>
+    /// whether a config of `type` matches the name, without rendering the match
+    pub fn matches_type(&self, short_name: &str, r#type: &str) -> bool
     {
-        for c in self.branches.iter().filter(|p| p.r#type == r#type)
-        {
-            if let Some(m) = c.try_match(short_name, id)?
-            {
-                return Ok(Some(m));
-            }
-        }
+        self.find_branches(r#type).any(|config| config.regex().is_match(short_name))
+    }
 
-        Ok(None)
+    /// fails if several configs of `type` match the name, see `check_ambiguous_match`
+    pub fn check_type_match(&self, short_name: &str, r#type: &str) -> anyhow::Result<()>
+    {
+        check_ambiguous_match(self.find_branches(r#type), short_name)
     }
-    
-    pub(crate) fn by_type<'a>(&'a self, r#type: &str) -> Option<&'a BranchConfig>
+
+    pub fn find_type_branch_config_for<'a>(&'a self, short_name: &str, id: Oid, r#type: &str) -> anyhow::Result<Option<BranchMatch<'a, S>>>
     {
-        self.branches.iter().filter(|e| e.r#type == r#type).next()
+        best_branch_match(self.branches.iter().filter(|p| p.r#type == r#type), short_name, id, None)
     }
 
-    /// removes the first occurence of "refs/heads/" or "refs/remotes/<tracked_origin>/"
+    /// removes the first occurence of "refs/heads/" or "refs/remotes/<tracked_origin>/",
+    /// pull request refs keep their path without "refs/", e.g. "pull/123/merge"
     /// if nothing could be removed `ref_name` is returned
     pub fn reference_name_to_branch_name<'a>(&self, ref_name: &'a str) -> &'a str
     {
@@ -251,7 +858,7 @@ impl Config
         let mut try_remove = |pat: &str|
         {
             let pre_len = result.len();
-            result = result.trim_start_matches(&pat);
+            result = result.trim_start_matches(pat);
             result.len() < pre_len
         };
 
@@ -266,7 +873,7 @@ impl Config
         {
             pattern.clear();
             pattern.push_str("refs/remotes/");
-            pattern.push_str(&origin);
+            pattern.push_str(origin);
             pattern.push('/');
 
             if try_remove(&pattern)
@@ -275,73 +882,105 @@ impl Config
             }
         }
 
+        // remove "refs/" of pull requests, some CI systems fetch them as "refs/remotes/pull/<number>/merge"
+        for prefix in ["refs/", "refs/remotes/"]
+        {
+            if let Some(pull_request) = result.strip_prefix(prefix).filter(|r| pull_request_number(r).is_some())
+            {
+                return pull_request;
+            }
+        }
+
         // nothing was removed, return unchanged
         result
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RawTagConfig
 {
     pub regex: String,
-    pub version: String
+    pub version: String,
+
+    /// if several tags on one commit match, the tag with the highest priority wins, then the highest version
+    #[serde(default)]
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub priority: Option<i32>
 }
 
 impl RawTagConfig
 {
-    pub fn parse(self, r#type: &str) -> anyhow::Result<TagConfig>
+    pub fn parse<S: VersionScheme>(self, r#type: &str, scheme: S) -> anyhow::Result<TagConfig<S>>
     {
         let regex = Regex::new(&self.regex)?;
+        let version = parse_template(&self.version, &regex, &TAG_VARIABLES).with_context(|| format!("invalid version of tag type {}", r#type))?;
+        validate_version_template(&scheme, &version).with_context(|| format!("invalid version of tag type {}", r#type))?;
         Ok(TagConfig
         {
             r#type: r#type.into(),
             regex,
+            version,
+            scheme,
             raw: self
         })
     }
 }
 
-pub struct TagConfig
+pub struct TagConfig<S: VersionScheme>
 {
     r#type: String,
     regex: Regex,
+    version: Template,
+    scheme: S,
     raw: RawTagConfig
 }
-impl TagConfig {
-    pub fn try_match<'a>(&'a self, tag: &str, id: Oid) -> anyhow::Result<Option<TagMatch<'a>>>
+impl<S: VersionScheme> TagConfig<S> {
+    pub fn raw(&self) -> &RawTagConfig {
+        &self.raw
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.raw.priority.unwrap_or_default()
+    }
+
+    pub fn try_match<'a>(&'a self, tag: &str, id: Oid) -> anyhow::Result<Option<TagMatch<'a, S>>>
     {
         let Some(captures) = self.regex.captures(tag) else { return Ok(None) };
-        let mut version_string = String::new();
-        let id_hash = id.to_string();
-        let tpl = self.raw.version.replace("$hash_short", &id_hash[..8]).replace("$hash", &id_hash);
-        captures.expand(&tpl, &mut version_string);
-        let Some(version) = SemVersion::parse(&version_string) else { bail!("{version_string} is an invalid version string") };
+        let version_string = self.version.render(|name| commit_variable(id, name)
+            .or_else(|| (name == "tag").then(|| tag.into()))
+            .or_else(|| capture(&self.regex, &captures, name)))?;
+        let version = self.scheme.parse(&version_string)?;
         Ok(Some(TagMatch{
-            config: &self,
+            config: self,
             tag: tag.into(),
             version
         }))
     }
 }
 
-pub struct TagMatch<'a>
+pub struct TagMatch<'a, S: VersionScheme>
 {
-    config: &'a TagConfig,
+    config: &'a TagConfig<S>,
     tag: String,
-    version: SemVersion
+    version: S::Version
 }
 
-impl<'a> TagMatch<'a> {
-    pub fn config(&self) -> &TagConfig {
+impl<'a, S: VersionScheme> TagMatch<'a, S> {
+    pub fn config(&self) -> &TagConfig<S> {
         self.config
     }
+
+    /// name of the matched tag
+    pub fn name(&self) -> &str {
+        &self.tag
+    }
     
-    pub fn version(&self) -> &SemVersion {
+    pub fn version(&self) -> &S::Version {
         &self.version
     }
 }
 
-impl Display for TagMatch<'_>
+impl<S: VersionScheme> Display for TagMatch<'_, S>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
     {
@@ -357,12 +996,18 @@ pub fn preset_config(preset: &ConfigPreset) -> anyhow::Result<RawConfig>
     {
         ConfigPreset::Releaseflow => RawConfig
         {
+            scheme: None,
             tracked_remotes: vec![ "origin".into() ],
+            ci_branch_variables: None,
+            label_precedence: vec![],
+            paths: None,
+            components: HashMap::new(),
             tags: HashMap::from([
                 ("release".into(), RawTagConfig
                 {
                     regex: r#"^v(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)$"#.into(),
-                    version: "$major.$minor.$patch".into()
+                    version: "$major.$minor.$patch".into(),
+                    priority: None
                 })
             ]),
             branches: HashMap::from([
@@ -374,7 +1019,13 @@ pub fn preset_config(preset: &ConfigPreset) -> anyhow::Result<RawConfig>
                     sources: vec!["main".into(), "release".into()],
                     base_version: None,
                     v_next: None,
-                    max_depth: None
+                    max_depth: None,
+                    first_parent: None,
+                    mainline: None,
+                    r#type: None,
+                    priority: None,
+                    conventional_commits: None,
+                    directives: None
                 }),
                 ("fix".into(), RawBranchConfig
                 {
@@ -384,7 +1035,29 @@ pub fn preset_config(preset: &ConfigPreset) -> anyhow::Result<RawConfig>
                     sources: vec!["main".into(), "release".into()],
                     base_version: None,
                     v_next: None,
-                    max_depth: None
+                    max_depth: None,
+                    first_parent: None,
+                    mainline: None,
+                    r#type: None,
+                    priority: None,
+                    conventional_commits: None,
+                    directives: None
+                }),
+                ("pull-request".into(), RawBranchConfig
+                {
+                    regex: r#"^(?:pull|merge-requests)/\d+/(?:merge|head)$"#.into(),
+                    label: Some("pr-$pr".into()),
+                    tracked: vec![],
+                    sources: vec!["main".into(), "release".into()],
+                    base_version: None,
+                    v_next: None,
+                    max_depth: None,
+                    first_parent: None,
+                    mainline: None,
+                    r#type: None,
+                    priority: None,
+                    conventional_commits: None,
+                    directives: None
                 }),
                 ("main".into(), RawBranchConfig
                 {
@@ -393,8 +1066,14 @@ pub fn preset_config(preset: &ConfigPreset) -> anyhow::Result<RawConfig>
                     tracked: vec!["release".into()],
                     sources: vec![],
                     base_version: Some("0.1.0".into()),
-                    v_next: Some(SemVersionInc::Minor(1)),
-                    max_depth: None
+                    v_next: Some(ConfigInc::Minor(1)),
+                    max_depth: None,
+                    first_parent: None,
+                    mainline: None,
+                    r#type: None,
+                    priority: None,
+                    conventional_commits: None,
+                    directives: None
                 }),
                 ("release".into(), RawBranchConfig
                 {
@@ -403,8 +1082,14 @@ pub fn preset_config(preset: &ConfigPreset) -> anyhow::Result<RawConfig>
                     tracked: vec![],
                     sources: vec!["main".into()],
                     base_version: Some("$major.$minor.0".into()),
-                    v_next: Some(SemVersionInc::Patch(1)),
-                    max_depth: None
+                    v_next: Some(ConfigInc::Patch(1)),
+                    max_depth: None,
+                    first_parent: None,
+                    mainline: None,
+                    r#type: None,
+                    priority: None,
+                    conventional_commits: None,
+                    directives: None
                 })
             ]),
         },
@@ -3,16 +3,18 @@
 mod config;
 pub mod cli;
 
-pub use config::{RawConfig, preset_config};
+pub use config::{RawConfig, RawScheme, preset_config};
 
-use std::{collections::HashMap, path::Path};
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, path::Path};
 
-use anyhow::{bail, Result};
-use config::{BranchMatch, Config, TagMatch};
-use git2::{Oid, Reference, Repository, Revwalk};
-use verner_core::{output::{ConsoleWriter, LogLevel}, semver::{SemVersion, SemVersionInc}, VersionHint, VersionInc};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use config::{BranchMatch, Config, Directive, PathFilter, RawPathFilter, TagMatch};
+use git2::{Commit, Oid, Reference, Repository, Revwalk};
+use verner_core::{calver::{CalDate, CalVerScheme}, explain::{ExplainedHistory, Explanation, Origin}, format::OutputFormat, output::{ConsoleWriter, LogLevel}, scheme::{CommitInfo, VersionScheme}, template::Template, VersionComponents, VersionHint, VersionInc, VersionOp};
 
 
+#[derive(Clone, Copy)]
 struct BranchSolveContext
 {
     depth: u32,
@@ -38,122 +40,315 @@ impl BranchSolveContext
     }
 }
 
-struct BranchSolver<'a, O: ConsoleWriter>
+/// branch type, branch name and commit of a solver
+type SolverKey = (String, String, Oid);
+
+type SchemeExplanation<S> = Explanation<<S as VersionScheme>::Version, <S as VersionScheme>::Inc>;
+
+/// tags and branches of the repository, collected once and shared by all solvers of a solve
+struct RepoRefs<'a, S: VersionScheme>
 {
-    output: &'a O,
-    current_branch: BranchMatch<'a>,
-    version_bases: HashMap<Oid, (SemVersion, BranchMatch<'a>)>,
-    branch_roots: HashMap<Oid, Option<BranchSolver<'a, O>>>,
-    tags: HashMap<Oid, TagMatch<'a>>,
-    rev_walk: Revwalk<'a>
+    tags: HashMap<Oid, TagMatch<'a, S>>,
+    branches: BTreeMap<String, Oid>,
+    /// versions of solved sources by branch type, branch name and commit, the name matters for labels and base versions
+    solved: RefCell<HashMap<SolverKey, S::Version>>,
+    /// explanations of solved sources, by the keys of `solved`
+    explained: RefCell<HashMap<SolverKey, SchemeExplanation<S>>>,
+    /// merge bases by the tips they were computed for, `None` if the histories are unrelated
+    merge_bases: RefCell<HashMap<(Oid, Oid), Option<Oid>>>,
+    /// commits since the merge base by tip and merge base
+    distances: RefCell<HashMap<(Oid, Oid), usize>>,
+    /// branch type, merge base and branch name of the solvers that are currently solving, from the outermost,
+    /// a source is solved at its merge base with the branch that started from it (the outermost at its tip)
+    solving: RefCell<Vec<(String, Oid, String)>>
 }
 
-impl<'a, O: ConsoleWriter> BranchSolver<'a, O>
+impl<'a, S: VersionScheme> RepoRefs<'a, S>
 {
-    pub fn new(ctx: BranchSolveContext, output: &'a O, cfg: &'a Config, repo: &'a Repository, branch: BranchMatch<'a>) -> Result<Self>
+    fn collect<O: ConsoleWriter>(output: &O, cfg: &'a Config<S>, repo: &Repository, refs: BranchRefs) -> Result<Self>
+    {
+        // find configured tags
+        let mut tag_candidates: HashMap<Oid, Vec<TagMatch<'a, S>>> = HashMap::new();
+        for reference in repo.references()?
+        {
+            let reference = reference?;
+            if reference.is_tag()
+            {
+                let Some(name) = reference.shorthand() else { continue };
+                // annotated tags (and tags of tags) point to tag objects, the version belongs to the tagged commit
+                let Ok(commit) = reference.peel_to_commit() else
+                {
+                    output.user_line(LogLevel::Trace, format!("ignoring tag {name}, it does not point to a commit"));
+                    continue;
+                };
+                let id = commit.id();
+                let Some(tag_match) = cfg.tags.iter().find_map(|e|e.try_match(name, id).transpose()) else { continue; };
+                // one malformed tag (e.g. v1.02.0) must not prevent solving
+                let tag_match = match tag_match
+                {
+                    Ok(tag_match) => tag_match,
+                    Err(err) =>
+                    {
+                        output.user_line(LogLevel::Warning, format!("ignoring tag {name}: {err:#}"));
+                        continue;
+                    }
+                };
+                tag_candidates.entry(id).or_default().push(tag_match);
+            }
+        }
+
+        let mut tags = HashMap::new();
+        for (id, candidates) in tag_candidates
+        {
+            let (tag, ignored) = select_tag(&cfg.scheme, candidates);
+            if !ignored.is_empty()
+            {
+                let ignored = ignored.iter().map(TagMatch::name).collect::<Vec<_>>().join(", ");
+                output.user_line(LogLevel::Warning, format!("commit {id} has several matching tags, using {} and ignoring {ignored}", tag.name()));
+            }
+            tags.insert(id, tag);
+        }
+
+        Ok(Self
+        {
+            tags,
+            branches: branch_tips(output, cfg, repo, refs)?,
+            solved: Default::default(),
+            explained: Default::default(),
+            merge_bases: Default::default(),
+            distances: Default::default(),
+            solving: Default::default()
+        })
+    }
+
+    /// the merge base of `tip` and the branch `name` at `other`, `None` if their histories are unrelated (e.g. gh-pages)
+    fn merge_base<O: ConsoleWriter>(&self, output: &O, repo: &Repository, tip: Oid, name: &str, other: Oid) -> Result<Option<Oid>>
     {
-        let mut branches = Vec::new();
-        for b in repo.branches(Some(git2::BranchType::Local))?
+        if let Some(merge_base) = self.merge_bases.borrow().get(&(tip, other))
         {
-            let (b, _) = b?;
-            branches.push(b.into_reference());
+            return Ok(*merge_base);
         }
 
+        let merge_base = match repo.merge_base(tip, other)
+        {
+            Ok(merge_base) => Some(merge_base),
+            Err(err) if err.code() == git2::ErrorCode::NotFound =>
+            {
+                output.user_line(LogLevel::Trace, format!("ignoring branch {name}, it has no common history with {tip}"));
+                None
+            },
+            Err(err) => return Err(err.into())
+        };
+        self.merge_bases.borrow_mut().insert((tip, other), merge_base);
+        Ok(merge_base)
+    }
+
+    /// the number of commits of `tip` since `merge_base`
+    fn distance(&self, repo: &Repository, tip: Oid, merge_base: Oid) -> Result<usize>
+    {
+        if let Some(distance) = self.distances.borrow().get(&(tip, merge_base))
+        {
+            return Ok(*distance);
+        }
+
+        let (distance, _) = repo.graph_ahead_behind(tip, merge_base)?;
+        self.distances.borrow_mut().insert((tip, merge_base), distance);
+        Ok(distance)
+    }
+}
+
+struct BranchSolver<'a, O: ConsoleWriter, S: VersionScheme>
+{
+    ctx: BranchSolveContext,
+    output: &'a O,
+    cfg: &'a Config<S>,
+    scheme: &'a S,
+    repo: &'a Repository,
+    refs: &'a RepoRefs<'a, S>,
+    current_branch: BranchMatch<'a, S>,
+    version_bases: HashMap<Oid, (S::Version, BranchMatch<'a, S>)>,
+    /// the root of the current branch on its source, the source is solved when the root is reached,
+    /// `None` if the base version of the current branch is used instead
+    branch_roots: HashMap<Oid, Option<BranchMatch<'a, S>>>,
+    /// sources as near as the solved source, reported if they would give a different version
+    source_ties: Vec<BranchMatch<'a, S>>,
+    rev_walk: Revwalk<'a>,
+    /// number of commits incrementing the version
+    commit_count: u32,
+    /// what provided the basis of the version
+    basis: Option<Basis>,
+    /// record the origin of every commit and explain nested solves
+    explain: bool,
+    origin: Option<Origin<S::Version, S::Inc>>,
+    /// increments requested by conventional commits or directives since the tip
+    bumps: Vec<S::Inc>,
+    /// the history was walked to the end without finding a basis
+    exhausted: bool,
+    /// only commits touching these paths count
+    paths: Option<&'a PathFilter>
+}
+
+impl<'a, O: ConsoleWriter, S: VersionScheme> BranchSolver<'a, O, S>
+{
+    pub fn new(ctx: BranchSolveContext, output: &'a O, cfg: &'a Config<S>, repo: &'a Repository, refs: &'a RepoRefs<'a, S>, branch: BranchMatch<'a, S>) -> Result<Self>
+    {
+
         let mut rev_walk = repo.revwalk()?;
+        // children before their parents: by date alone, a merged commit that is not newer than the basis it is based on
+        // (rebased, or committed in the same second) is walked after the basis and not counted,
+        // see the tests commits_dated_before_the_tag_are_counted and pull_request_merge_ref_uses_first_parent_as_target
+        rev_walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
         rev_walk.push(branch.tip())?;
+        if branch.config().raw().first_parent.unwrap_or(false) || branch.config().mainline().is_some()
+        {
+            rev_walk.simplify_first_parent()?;
+        }
 
         let mut solver = Self
         {
+            ctx,
             output,
+            cfg,
+            scheme: &cfg.scheme,
+            repo,
+            refs,
             current_branch: branch.clone(),
             version_bases: Default::default(),
             branch_roots: Default::default(),
+            source_ties: Vec::new(),
             rev_walk,
-            tags: Default::default()
+            commit_count: 0,
+            basis: None,
+            explain: false,
+            origin: None,
+            bumps: Vec::new(),
+            exhausted: false,
+            paths: cfg.paths.as_ref()
         };
 
-        // find configured tags
-        for reference in repo.references()?
+        for origin in branch.config().raw().sources.iter()
         {
-            let reference = reference?;
-            if reference.is_tag()
+            if cfg.find_branches(origin).next().is_none()
             {
-                let Some(name) = reference.shorthand() else { continue };
-                let Some(id) = reference.target() else { continue };
-                let Some(tag_match) = cfg.tags.iter().find_map(|e|e.try_match(name, id).transpose()) else { continue; };
-                let tag_match = tag_match?;
-                solver.tags.insert(id, tag_match);
+                bail!("could not find config for origin branch with type: {origin}");
             }
-            else
+        }
+
+        // candidates for the start of the current branch: (commits since the merge base, index in `sources`, merge base, source)
+        let mut source_candidates: Vec<(usize, usize, Oid, BranchMatch<'a, S>)> = Vec::new();
+        for (name, tip) in refs.branches.iter()
+        {
+            let (name, tip) = (name.as_str(), *tip);
+
+            let origins: Vec<(usize, &String)> = branch.config().raw().sources.iter().enumerate()
+                .filter(|(_, origin)| cfg.matches_type(name, origin))
+                .collect();
+            if !origins.is_empty() && branch.pr_target().is_none_or(|pr_target| pr_target == name)
             {
-                // find start of the current branch
-                for origin in branch.config().raw().sources.iter()
+                if let Some(merge_base) = refs.merge_base(output, repo, branch.tip(), name, tip)?
                 {
-                    if let Some(origin_cfg) = cfg.by_type(origin)
+                    let distance = refs.distance(repo, branch.tip(), merge_base)?;
+                    for (index, origin) in origins
                     {
-                        let Some(name) = reference.name() else { continue; };
-                        let name = &cfg.reference_name_to_branch_name(name);
-                        let Some(tip) = reference.target() else { continue; };
-                        let merge_base = repo.merge_base(branch.tip(), tip)?;
-                        let Some(source_match) = origin_cfg.try_match(name, merge_base)? else { continue };
-
-                        let source_solver = if branch.base_version().is_none()
-                        {ctx.try_descend().map(|ctx| BranchSolver::new(ctx, output, cfg, repo, source_match)).transpose()?}
-                        else { None };
-                        solver.branch_roots.insert(merge_base, source_solver);
-                    }
-                    else
-                    {
-                        bail!("could not find config for origin branch with type: {origin}");
+                        // the branch being solved fails on overlapping configs, a candidate is only skipped
+                        if let Err(err) = cfg.check_type_match(name, origin)
+                        {
+                            output.user_line(LogLevel::Warning, format!("ignoring source {name}: {err:#}"));
+                            continue;
+                        }
+                        let Some(source_match) = cfg.find_type_branch_config_for(name, merge_base, origin)? else { continue };
+                        source_candidates.push((distance, index, merge_base, source_match));
                     }
                 }
+            }
 
-                for tracked in branch.config().raw().tracked.iter()
+            for tracked in branch.config().raw().tracked.iter()
+            {
+                if cfg.find_branches(tracked).next().is_none()
                 {
-                    if let Some(tracked_cfg) = cfg.by_type(tracked)
-                    {
-                        let Some(name) = reference.name() else { continue; };
-                        let name = &cfg.reference_name_to_branch_name(name);
-                        let Some(target) = reference.target() else { continue; };
-                        let Some(tracked_match) = tracked_cfg.try_match(name, target)? else { continue };
-                        let merge_base = repo.merge_base(branch.tip(), tracked_match.tip())?;
+                    bail!("could not find config for tracked branch with type: {tracked}");
+                }
 
-                        if let Some(tracked_base) = tracked_match.base_version()
-                        {
-                            solver.version_bases.insert(merge_base, (tracked_base.clone(), tracked_match));
-                        }
-                    }
-                    else
+                if let Err(err) = cfg.check_type_match(name, tracked)
+                {
+                    output.user_line(LogLevel::Warning, format!("ignoring tracked branch {name}: {err:#}"));
+                    continue;
+                }
+                let Some(tracked_match) = cfg.find_type_branch_config_for(name, tip, tracked)? else { continue };
+                let Some(merge_base) = refs.merge_base(output, repo, branch.tip(), name, tracked_match.tip())? else { continue };
+
+                if let Some(tracked_base) = tracked_match.base_version()
+                {
+                    solver.version_bases.insert(merge_base, (tracked_base.clone(), tracked_match));
+                }
+            }
+        }
+
+        // the nearest source is the one the current branch started from, earlier `sources` win ties
+        source_candidates.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)).then_with(|| a.3.name().cmp(b.3.name())));
+        if let Some((distance, _, merge_base, source_match)) = source_candidates.first()
+        {
+            output.user_line(LogLevel::Trace, format!("source of {} is {} ({}), {distance} commits since {merge_base}", branch.name(), source_match.name(), source_match.config().r#type()));
+
+            let source = (branch.base_version().is_none() && ctx.try_descend().is_some()).then(|| source_match.clone());
+            if source.is_some()
+            {
+                // a branch matching several types in `sources` is one candidate
+                for (_, _, _, tie) in source_candidates.iter().skip(1).filter(|c| c.0 == *distance)
+                {
+                    if tie.name() != source_match.name() && !solver.source_ties.iter().any(|t| t.name() == tie.name())
                     {
-                        bail!("could not find config for tracked branch with type: {tracked}");
+                        solver.source_ties.push(tie.clone());
                     }
                 }
             }
+            solver.branch_roots.insert(*merge_base, source);
         }
 
         Ok(solver)
     }
 
-    fn solve_inc_for_commit(&mut self, id: Oid) -> anyhow::Result<VersionInc<SemVersion, SemVersionInc>>
+    fn solve_inc_for_commit(&mut self, id: Oid) -> anyhow::Result<VersionInc<S::Version, S::Inc>>
     {
-        if let Some(tag) = self.tags.get(&id)
+        if let Some(tag) = self.refs.tags.get(&id)
         {
             self.output.user_line(LogLevel::Trace, format!("{id} -> Found tag {tag}"));
+            self.basis = Some(Basis::Tag { commit: id.to_string(), tag: tag.name().into() });
+            self.origin = Some(Origin { node: id.to_string(), reason: format!("tag {}", tag.name()), nested: None });
+            if let Some(bumped) = self.bumped(tag.version())
+            {
+                return Ok(VersionInc::HardBasis(bumped));
+            }
             return Ok(VersionInc::Fixed(tag.version().clone())); // fixed since a tagged commit has the tagged version, and the following commits it is vNext
         }
 
-        if let Some(source_solver) = self.branch_roots.get_mut(&id)
+        if let Some(source) = self.branch_roots.get(&id).cloned()
         {
-            if let Some(source_solver) = source_solver
+            if let Some(source) = source
             {
-                self.output.user_line(LogLevel::Trace, format!("{id} -> Found branch root, now solving for {}", source_solver.current_branch.config().r#type()));
-                let source_version = source_solver.solve()?;
-                return Ok(VersionInc::SoftBasis(source_version.erase_build())); // soft basis since the solved value is vNext of the source branch
+                self.output.user_line(LogLevel::Trace, format!("{id} -> Found branch root, now solving for {}", source.config().r#type()));
+                let (source_version, nested) = if self.explain
+                {
+                    let explanation = self.explain_source(source.clone())?;
+                    (explanation.version.clone(), Some(Box::new(explanation)))
+                }
+                else { (self.solve_source(source.clone())?, None) };
+                let source_version = self.scheme.erase_build(&source_version);
+                self.check_source_ties(&source, &source_version);
+                self.basis = Some(Basis::Source { commit: id.to_string(), branch_type: source.config().r#type().into(), branch: source.name().into() });
+                self.origin = Some(Origin { node: id.to_string(), reason: format!("branch root on {} ({})", source.name(), source.config().r#type()), nested });
+                if let Some(bumped) = self.bumped(&source_version)
+                {
+                    return Ok(VersionInc::HardBasis(bumped));
+                }
+                return Ok(VersionInc::SoftBasis(source_version)); // soft basis since the solved value is vNext of the source branch
             }
             else
             {
-                let base_version = self.current_branch.base_version().map(|v|v.clone()).unwrap_or_default();
+                let base_version = self.current_branch.base_version().cloned().unwrap_or_else(|| self.scheme.default_basis());
                 self.output.user_line(LogLevel::Trace, format!("{id} -> Found branch root, using base version {}", &base_version));
+                self.basis = Some(Basis::BaseVersion { commit: id.to_string() });
+                self.origin = Some(Origin { node: id.to_string(), reason: "branch root".into(), nested: None });
                 return Ok(VersionInc::HardBasis(base_version)); // hard basis since the branch root is already vNext
             }
         }
@@ -161,31 +356,294 @@ impl<'a, O: ConsoleWriter> BranchSolver<'a, O>
         if let Some((base_version, source_branch_match)) = self.version_bases.get(&id)
         {
             self.output.user_line(LogLevel::Trace, format!("{id} -> Found root of tracked branch (type: {} version: {})", source_branch_match.config().r#type(), &base_version));
+            self.basis = Some(Basis::Tracked { commit: id.to_string(), branch_type: source_branch_match.config().r#type().into(), branch: source_branch_match.name().into() });
+            self.origin = Some(Origin { node: id.to_string(), reason: format!("root of tracked branch {} ({})", source_branch_match.name(), source_branch_match.config().r#type()), nested: None });
+            if let Some(bumped) = self.bumped(base_version)
+            {
+                return Ok(VersionInc::HardBasis(bumped));
+            }
             return Ok(VersionInc::SoftBasis(base_version.clone()));
         }
 
-        self.output.user_line(LogLevel::Trace, format!("{id} -> Increment build by 1"));
-        Ok(VersionInc::Inc(SemVersionInc::Build(1)))
+        let commit = self.repo.find_commit(id)?;
+        if let Some(paths) = self.paths
+        {
+            if !touches_paths(self.repo, &commit, paths)?
+            {
+                self.output.user_line(LogLevel::Trace, format!("{id} -> Skipped, no changes in paths"));
+                self.origin = Some(Origin { node: id.to_string(), reason: "no changes in paths".into(), nested: None });
+                return Ok(VersionInc::Skip);
+            }
+        }
+
+        let message = commit.message().unwrap_or_default();
+        let config = self.current_branch.config();
+        let directive = config.directives().map(|directives| directives.find(message)).transpose().with_context(|| format!("invalid directive in commit {id}"))?.flatten();
+
+        let bump = match directive
+        {
+            Some(Directive::Skip) =>
+            {
+                self.output.user_line(LogLevel::Trace, format!("{id} -> Skipped by directive"));
+                self.origin = Some(Origin { node: id.to_string(), reason: "skip directive".into(), nested: None });
+                return Ok(VersionInc::Skip);
+            },
+            Some(Directive::ReleaseAs(version)) =>
+            {
+                let version = self.scheme.parse(version).with_context(|| format!("invalid release_as directive in commit {id}"))?;
+                self.output.user_line(LogLevel::Trace, format!("{id} -> Release as {version} by directive"));
+                self.basis = Some(Basis::ReleaseAs { commit: id.to_string(), version: version.to_string() });
+                self.origin = Some(Origin { node: id.to_string(), reason: "release_as directive".into(), nested: None });
+                if let Some(bumped) = self.bumped(&version)
+                {
+                    return Ok(VersionInc::HardBasis(bumped));
+                }
+                return Ok(VersionInc::HardBasis(version));
+            },
+            Some(Directive::Bump(inc)) => inc.cloned().map(|inc| (inc, "bump directive")),
+            None => config.conventional_commits().and_then(|cc| cc.inc_for(message)).cloned().map(|inc| (inc, "conventional commit"))
+        };
+
+        if let Some(mainline) = config.mainline().filter(|_| commit.parent_count() > 1)
+        {
+            let merged = self.merged_bump(&commit)?;
+            let (inc, reason) = match (bump, merged)
+            {
+                (Some((bump, source)), _) => (bump, format!("mainline increment, {source} requests it")),
+                (None, Some(merged)) => (merged, "mainline increment requested by merged commits".into()),
+                (None, None) => (mainline.clone(), "mainline increment".into())
+            };
+            self.output.user_line(LogLevel::Trace, format!("{id} -> Mainline increment by {inc}"));
+            self.commit_count += 1;
+            self.origin = Some(Origin { node: id.to_string(), reason, nested: None });
+            return Ok(VersionInc::Inc(inc));
+        }
+
+        // the mainline strategy bumps only on merges, a direct commit counts as a build increment
+        let bump = bump.filter(|_| config.mainline().is_none());
+        let time = commit.time();
+        let inc = self.scheme.commit_inc(&CommitInfo { time: time.seconds(), offset_minutes: time.offset_minutes() });
+        self.output.user_line(LogLevel::Trace, format!("{id} -> Increment by {inc}"));
+        self.commit_count += 1;
+
+        let reason = match bump
+        {
+            Some((ref bump, source)) =>
+            {
+                self.output.user_line(LogLevel::Trace, format!("{id} -> {source} requests {bump}"));
+                format!("build increment, {source} requests {bump}")
+            },
+            None => "build increment".into()
+        };
+        let bump = bump.map(|(bump, _)| bump);
+        self.bumps.extend(bump);
+        self.origin = Some(Origin { node: id.to_string(), reason, nested: None });
+        Ok(VersionInc::Inc(inc))
     }
 
-    pub fn solve(&mut self) -> Result<SemVersion>
+    /// solver of a source branch, constructed when its root is reached
+    fn source_solver(&self, source: BranchMatch<'a, S>) -> Result<BranchSolver<'a, O, S>>
     {
-        self.solve_raw(self.current_branch.config().raw().v_next.clone())
+        let Some(ctx) = self.ctx.try_descend() else { bail!("maximum solving depth reached") };
+        BranchSolver::new(ctx, self.output, self.cfg, self.repo, self.refs, source)
     }
 
-    fn solve_raw(&mut self, v_next: Option<SemVersionInc>) -> Result<SemVersion>
+    /// version of a source branch, memoized for the whole solve
+    fn solve_source(&self, source: BranchMatch<'a, S>) -> Result<S::Version>
     {
-        let basis = self.current_branch.base_version().map(Clone::clone).unwrap_or_else(|| SemVersion::default());
-        let tag = self.current_branch.tag().map(|tag|tag.to_string());
-        let (mut version, hint) = verner_core::resolve_version(self, basis, v_next)?;
-        if hint != VersionHint::Fixed { version = version.with_label(tag.into()); }
+        let key = (source.config().r#type().to_string(), source.name().to_string(), source.tip());
+        let solved = self.refs.solved.borrow().get(&key).cloned();
+        if let Some(version) = solved
+        {
+            self.output.user_line(LogLevel::Trace, format!("{} -> Using solved version {version} of {}", key.2, key.1));
+            return Ok(version);
+        }
+
+        let (version, _) = self.source_solver(source)?.solve()?;
+        self.refs.solved.borrow_mut().insert(key, version.clone());
         Ok(version)
     }
+
+    /// warns about sources as near as `source` that would give a different version
+    fn check_source_ties(&self, source: &BranchMatch<'a, S>, version: &S::Version)
+    {
+        let version = self.scheme.format(version);
+        let differing: Vec<String> = self.source_ties.iter()
+            .filter_map(|tie| match self.solve_source(tie.clone())
+            {
+                Ok(tie_version) => Some(self.scheme.format(&self.scheme.erase_build(&tie_version))).filter(|tie_version| *tie_version != version).map(|tie_version| format!("{} ({tie_version})", tie.name())),
+                Err(err) => Some(format!("{} ({err:#})", tie.name()))
+            })
+            .collect();
+
+        if !differing.is_empty()
+        {
+            self.output.user_line(LogLevel::Warning, format!("sources of {} are equally near but differ, using {} ({version}) and ignoring {}", self.current_branch.name(), source.name(), differing.join(", ")));
+        }
+    }
+
+    /// explanation of a source branch, memoized like `solve_source`
+    fn explain_source(&self, source: BranchMatch<'a, S>) -> Result<SchemeExplanation<S>>
+    {
+        let key = (source.config().r#type().to_string(), source.name().to_string(), source.tip());
+        let explained = self.refs.explained.borrow().get(&key).cloned();
+        if let Some(explanation) = explained
+        {
+            self.output.user_line(LogLevel::Trace, format!("{} -> Using explained version {} of {}", key.2, explanation.version, key.1));
+            return Ok(explanation);
+        }
+
+        let explanation = self.source_solver(source)?.explain()?;
+        self.refs.solved.borrow_mut().insert(key.clone(), explanation.version.clone());
+        self.refs.explained.borrow_mut().insert(key, explanation.clone());
+        Ok(explanation)
+    }
+
+    /// the highest increment requested by the commits that `commit` merges into the first-parent chain
+    fn merged_bump(&self, commit: &Commit) -> Result<Option<S::Inc>>
+    {
+        if commit.parent_count() < 2
+        {
+            return Ok(None);
+        }
+
+        let config = self.current_branch.config();
+        let mut merged = self.repo.revwalk()?;
+        for parent in commit.parent_ids().skip(1)
+        {
+            merged.push(parent)?;
+        }
+        merged.hide(commit.parent_id(0)?)?;
+
+        let mut bumps = Vec::new();
+        for id in merged
+        {
+            let id = id?;
+            let merged_commit = self.repo.find_commit(id)?;
+            let message = merged_commit.message().unwrap_or_default();
+            let directive = config.directives().map(|directives| directives.find(message)).transpose().with_context(|| format!("invalid directive in commit {id}"))?.flatten();
+            let bump = match directive
+            {
+                Some(Directive::Bump(inc)) => inc,
+                Some(_) => None,
+                None => config.conventional_commits().and_then(|cc| cc.inc_for(message))
+            };
+            bumps.extend(bump.cloned());
+        }
+
+        let basis = self.scheme.default_basis();
+        Ok(bumps.into_iter()
+            .map(|bump|
+            {
+                let mut version = basis.clone();
+                version.inc(&bump);
+                (version, bump)
+            })
+            .max_by(|(a, _), (b, _)| self.scheme.cmp_precedence(a, b))
+            .map(|(_, bump)| bump))
+    }
+
+    /// vNext of the branch, the mainline strategy increments every commit instead
+    fn v_next(&self) -> Option<S::Inc>
+    {
+        let config = self.current_branch.config();
+        if config.mainline().is_some() { None } else { config.v_next().cloned() }
+    }
+
+    /// `basis` with the highest increment requested by conventional commits or directives, which replaces vNext
+    fn bumped(&self, basis: &S::Version) -> Option<S::Version>
+    {
+        self.bumps.iter()
+            .map(|bump|
+            {
+                let mut version = basis.clone();
+                version.inc(bump);
+                version
+            })
+            .max_by(|a, b| self.scheme.cmp_precedence(a, b))
+    }
+
+    pub fn solve(&mut self) -> Result<(S::Version, VersionHint)>
+    {
+        self.solve_raw(self.v_next())
+    }
+
+    fn solve_raw(&mut self, v_next: Option<S::Inc>) -> Result<(S::Version, VersionHint)>
+    {
+        let basis = self.current_branch.base_version().cloned().unwrap_or_else(|| self.scheme.default_basis());
+        let tag = self.current_branch.tag().map(|tag|tag.to_string());
+        let (mut version, hint) = self.solving(|solver| verner_core::resolve_version(solver, basis, v_next))?;
+        if hint != VersionHint::Fixed { version = self.scheme.with_label(&version, tag.as_deref()); }
+        Ok((version, hint))
+    }
+
+    /// runs `f` while the current branch is being solved, fails if the same branch type is already being solved at the same merge base
+    fn solving<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T>
+    {
+        // the tip of a source solver is the merge base
+        let entry = (self.current_branch.config().r#type().to_string(), self.current_branch.tip(), self.current_branch.name().to_string());
+        {
+            let solving = self.refs.solving.borrow();
+            if let Some(start) = solving.iter().position(|(r#type, merge_base, _)| *r#type == entry.0 && *merge_base == entry.1)
+            {
+                let cycle = solving[start..].iter().chain(std::iter::once(&entry))
+                    .map(|(r#type, merge_base, name)| format!("{name} ({type}) at {merge_base}"))
+                    .collect::<Vec<_>>();
+                bail!("branch sources form a cycle: {}", cycle.join(" -> "));
+            }
+        }
+
+        self.refs.solving.borrow_mut().push(entry);
+        let result = f(self);
+        self.refs.solving.borrow_mut().pop();
+        result
+    }
+
+    /// solves the version and records how every commit contributed to it
+    pub fn explain(&mut self) -> Result<Explanation<S::Version, S::Inc>>
+    {
+        self.explain = true;
+        let basis = self.current_branch.base_version().cloned().unwrap_or_else(|| self.scheme.default_basis());
+        let v_next = self.v_next();
+        let mut explanation = self.solving(|solver| verner_core::resolve_version_explained(solver, basis, v_next))?;
+        if explanation.hint != VersionHint::Fixed { explanation.version = self.scheme.with_label(&explanation.version, self.current_branch.tag()); }
+        Ok(explanation)
+    }
 }
 
-impl<'a, O: ConsoleWriter> Iterator for BranchSolver<'a, O>
+impl<O: ConsoleWriter, S: VersionScheme> ExplainedHistory<S::Version, S::Inc> for BranchSolver<'_, O, S>
 {
-    type Item = Result<VersionInc<SemVersion, SemVersionInc>>;
+    fn origin(&mut self) -> Origin<S::Version, S::Inc>
+    {
+        self.origin.take().unwrap_or_else(|| Origin { node: String::new(), reason: "unknown".into(), nested: None })
+    }
+}
+
+/// whether `commit` changes a path matched by `paths`, compared to its first parent
+fn touches_paths(repo: &Repository, commit: &Commit, paths: &PathFilter) -> Result<bool>
+{
+    let tree = commit.tree()?;
+    let parent_tree = if commit.parent_count() > 0 { Some(commit.parent(0)?.tree()?) } else { None };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+    Ok(diff.deltas().any(|delta| [delta.old_file().path(), delta.new_file().path()].into_iter().flatten().any(|path| paths.matches(path))))
+}
+
+/// chooses the tag with the highest priority, then the highest version (by the scheme's label precedence) and then the lowest name
+/// and returns it together with the ignored tags
+fn select_tag<'a, S: VersionScheme>(scheme: &S, mut candidates: Vec<TagMatch<'a, S>>) -> (TagMatch<'a, S>, Vec<TagMatch<'a, S>>)
+{
+    candidates.sort_by(|a, b| b.config().priority().cmp(&a.config().priority())
+        .then_with(|| scheme.cmp_precedence(b.version(), a.version()))
+        .then_with(|| a.name().cmp(b.name())));
+
+    let tag = candidates.remove(0);
+    (tag, candidates)
+}
+
+impl<'a, O: ConsoleWriter, S: VersionScheme> Iterator for BranchSolver<'a, O, S>
+{
+    type Item = Result<VersionInc<S::Version, S::Inc>>;
     
     fn next(&mut self) -> Option<Self::Item>
     {
@@ -197,19 +655,120 @@ impl<'a, O: ConsoleWriter> Iterator for BranchSolver<'a, O>
             }
         }
 
+        // without a basis in the history, requested bumps apply to the branch's base version
+        if !self.exhausted && !self.bumps.is_empty()
+        {
+            self.exhausted = true;
+            let basis = self.current_branch.base_version().cloned().unwrap_or_else(|| self.scheme.default_basis());
+            self.origin = Some(Origin { node: "-".into(), reason: "end of history".into(), nested: None });
+            return self.bumped(&basis).map(|bumped| Ok(VersionInc::HardBasis(bumped)));
+        }
+
         None
     }
     
 }
 
-fn resolve_current_branch<'repo, C: ConsoleWriter>(c: &C, cfg: &Config, repo: &'repo Repository) -> anyhow::Result<Reference<'repo>>
+/// the branch a pull request merge commit merges into: the configured branch pointing to its first parent
+fn detect_pr_target<S: VersionScheme>(cfg: &Config<S>, repo: &Repository, tip: Oid) -> Result<Option<String>>
+{
+    let commit = repo.find_commit(tip)?;
+    if commit.parent_count() < 2
+    {
+        return Ok(None);
+    }
+    let target = commit.parent_id(0)?;
+
+    let mut found = Vec::new();
+    for b in repo.branches(None)?
+    {
+        let r = b?.0.into_reference();
+        let Some(name) = r.name() else { continue; };
+        let name = cfg.reference_name_to_branch_name(name);
+        if r.target() == Some(target) && config::pull_request_number(name).is_none() && cfg.try_match_branch(name, target)?.is_some()
+        {
+            found.push(name.to_string());
+        }
+    }
+    found.sort();
+    found.dedup();
+
+    Ok(if found.len() == 1 { found.pop() } else { None })
+}
+
+/// which branch refs provide sources and tracked branches
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BranchRefs
+{
+    /// local branches and branches of tracked remotes
+    #[default]
+    All,
+    /// only `refs/heads/*`
+    Local,
+    /// only `refs/remotes/<tracked remote>/*`
+    Remote
+}
+
+/// tips of the branches in `refs` by short name
+///
+/// if a local branch and a remote branch of the same name point to different commits, the more advanced one is used,
+/// if they diverged the local branch is used
+fn branch_tips<O: ConsoleWriter, S: VersionScheme>(output: &O, cfg: &Config<S>, repo: &Repository, refs: BranchRefs) -> Result<BTreeMap<String, Oid>>
+{
+    let mut tips: BTreeMap<String, (Oid, bool)> = BTreeMap::new();
+    for reference in repo.references()?
+    {
+        let reference = reference?;
+        let local = reference.is_branch();
+        let selected = match refs
+        {
+            BranchRefs::All => local || reference.is_remote(),
+            BranchRefs::Local => local,
+            BranchRefs::Remote => reference.is_remote()
+        };
+        let (Some(full_name), Some(tip)) = (reference.name(), reference.target()) else { continue; };
+        let name = cfg.reference_name_to_branch_name(full_name);
+        // branches of remotes that are not tracked keep their full name
+        if !selected || name == full_name
+        {
+            continue;
+        }
+
+        let Some(&(other_tip, other_local)) = tips.get(name) else
+        {
+            tips.insert(name.to_string(), (tip, local));
+            continue;
+        };
+        if other_tip == tip || repo.graph_descendant_of(other_tip, tip)?
+        {
+            continue;
+        }
+        if repo.graph_descendant_of(tip, other_tip)?
+        {
+            output.user_line(LogLevel::Trace, format!("using {full_name}, it is ahead of the {} branch {name}", if other_local { "local" } else { "remote" }));
+            tips.insert(name.to_string(), (tip, local));
+            continue;
+        }
+
+        output.user_line(LogLevel::Warning, format!("local and remote branch {name} diverged, using the local branch"));
+        if local
+        {
+            tips.insert(name.to_string(), (tip, local));
+        }
+    }
+
+    Ok(tips.into_iter().map(|(name, (tip, _))| (name, tip)).collect())
+}
+
+/// name and tip of the checked out branch, on a detached HEAD the branch pointing to it or the branch named by a CI variable
+fn resolve_current_branch<C: ConsoleWriter, S: VersionScheme>(c: &C, cfg: &Config<S>, repo: &Repository, env: impl Fn(&str) -> Option<String>) -> anyhow::Result<(String, Oid)>
 {
     let head = repo.head()?;
+    let Some(head_id) = head.target() else { bail!("Head does not point to a commit") };
     if repo.head_detached()?
     {
         c.user_line(LogLevel::Info, "HEAD is detached");
 
-        let Some(head_id) = head.target() else { bail!("Head does not point to a commit") };
         let mut found = Vec::new();
 
         for b in repo.branches(None)?
@@ -221,45 +780,317 @@ fn resolve_current_branch<'repo, C: ConsoleWriter>(c: &C, cfg: &Config, repo: &'
             if head_id == id
             {
                 c.user_line(LogLevel::Info, format!("found reference {name}"));
-                found.push(r);
+                found.push(name.to_string());
             }
         }
 
         if found.len() == 1
         {
-            let found = found.into_iter().next().unwrap();
-            let Some(name) = found.name() else { bail!("Branch has no name") };
-            let name = cfg.reference_name_to_branch_name(name);
+            let name = cfg.reference_name_to_branch_name(&found[0]);
             c.user_line(LogLevel::Info, format!("Using branch {}", name));
-            return Ok(found);
+            Ok((name.to_string(), head_id))
+        }
+        else if let Some(name) = branch_from_ci_env(c, cfg, head_id, env)?
+        {
+            Ok((name, head_id))
         }
         else
         {
-            c.user_line(LogLevel::Error, format!("Found {} branches pointing to {head_id}. Try specifying the branch explicitly.", found.len()));
-            bail!("found multiple references pointing to {head_id}");
+            c.user_line(LogLevel::Error, format!("Found {} branches pointing to {head_id} and no CI variable names a configured branch. Try specifying the branch explicitly.", found.len()));
+            bail!("found {} references pointing to {head_id}", found.len());
         }
     }
     else
     {
-        Ok(head)
+        let Some(name) = head.name() else { bail!("Branch has no name") };
+        Ok((cfg.reference_name_to_branch_name(name).to_string(), head_id))
+    }
+}
+
+/// the first of the configured CI variables that names a configured branch
+fn branch_from_ci_env<C: ConsoleWriter, S: VersionScheme>(c: &C, cfg: &Config<S>, head_id: Oid, env: impl Fn(&str) -> Option<String>) -> anyhow::Result<Option<String>>
+{
+    for variable in cfg.ci_branch_variables.iter()
+    {
+        let Some(value) = env(variable).filter(|value| !value.is_empty()) else { continue; };
+        let name = cfg.reference_name_to_branch_name(&value);
+        if cfg.try_match_branch(name, head_id)?.is_none()
+        {
+            c.user_line(LogLevel::Trace, format!("ignoring CI variable {variable}={value}, it does not match a configured branch"));
+            continue;
+        }
+
+        c.user_line(LogLevel::Info, format!("Using branch {name} from CI variable {variable}"));
+        return Ok(Some(name.to_string()));
+    }
+
+    Ok(None)
+}
+
+/// the commit that provided the basis of a solved version
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Basis
+{
+    /// a tagged commit
+    Tag { commit: String, tag: String },
+
+    /// the merge base with a tracked branch that has a base version
+    Tracked { commit: String, branch_type: String, branch: String },
+
+    /// the root of the current branch on a source branch
+    Source { commit: String, branch_type: String, branch: String },
+
+    /// the root of the current branch, using its base version
+    BaseVersion { commit: String },
+
+    /// a commit with a release_as directive
+    ReleaseAs { commit: String, version: String }
+}
+
+impl Basis
+{
+    pub fn commit(&self) -> &str
+    {
+        match self
+        {
+            Basis::Tag { commit, .. } | Basis::Tracked { commit, .. } | Basis::Source { commit, .. } | Basis::BaseVersion { commit } | Basis::ReleaseAs { commit, .. } => commit
+        }
+    }
+
+    pub fn tag(&self) -> Option<&str>
+    {
+        match self
+        {
+            Basis::Tag { tag, .. } => Some(tag),
+            _ => None
+        }
+    }
+}
+
+/// names of the variables `Solution::variable` provides
+pub const SOLUTION_VARIABLES: [&str; 15] = ["version", "major", "minor", "patch", "release", "label", "build", "metadata", "branch", "branch_type", "sha", "sha_short", "commit_count", "commit_date", "tag"];
+
+/// fails if the template uses a variable no solution provides, so a typo is reported before solving
+pub fn check_template(template: &Template) -> Result<()>
+{
+    if let Some(name) = template.variables().find(|name| !SOLUTION_VARIABLES.contains(name))
+    {
+        bail!("unknown variable '{name}' in template '{}', known variables: {}", template.source(), SOLUTION_VARIABLES.join(", "));
+    }
+    Ok(())
+}
+
+/// a solved version and the repository state it was solved for
+pub struct Solution<V = Box<dyn VersionComponents>>
+{
+    pub version: V,
+    /// the version formatted by its scheme
+    pub text: String,
+    pub branch_type: String,
+    /// short name of the branch
+    pub branch_name: String,
+    /// id of the commit the version was solved for
+    pub sha: String,
+    pub commit_time: CommitInfo,
+    /// number of commits since the basis of the version
+    pub commit_count: u32,
+    /// what provided the basis, `None` if the history was exhausted
+    pub basis: Option<Basis>,
+    pub hint: VersionHint,
+    /// the derivation of the version, only recorded for `verner git explain`
+    pub explanation: Option<String>
+}
+
+impl<V: VersionComponents + 'static> Solution<V>
+{
+    pub fn boxed(self) -> Solution
+    {
+        Solution
+        {
+            version: Box::new(self.version),
+            text: self.text,
+            branch_type: self.branch_type,
+            branch_name: self.branch_name,
+            sha: self.sha,
+            commit_time: self.commit_time,
+            commit_count: self.commit_count,
+            basis: self.basis,
+            hint: self.hint,
+            explanation: self.explanation
+        }
+    }
+}
+
+impl Solution
+{
+    /// variables of output templates, see `SOLUTION_VARIABLES`
+    pub fn variable(&self, name: &str) -> Option<String>
+    {
+        let version = self.version.as_ref();
+        let release = version.release();
+        let segment = |i: usize| release.get(i).copied().unwrap_or_default().to_string();
+
+        Some(match name
+        {
+            "version" => self.text.clone(),
+            "major" => segment(0),
+            "minor" => segment(1),
+            "patch" => segment(2),
+            "release" => release.iter().map(ToString::to_string).collect::<Vec<_>>().join("."),
+            "label" => version.label().unwrap_or_default().into(),
+            "build" => version.build().to_string(),
+            "metadata" => version.metadata().unwrap_or_default().into(),
+            "branch" => self.branch_name.clone(),
+            "branch_type" => self.branch_type.clone(),
+            "sha" => self.sha.clone(),
+            "sha_short" => self.sha[..8].into(),
+            "commit_count" => self.commit_count.to_string(),
+            "commit_date" => CalDate::from_unix(self.commit_time.time, self.commit_time.offset_minutes).to_string(),
+            "tag" => self.basis.as_ref().and_then(Basis::tag).unwrap_or_default().into(),
+            _ => return None
+        })
+    }
+}
+
+/// json view of a solution
+#[derive(Serialize)]
+struct SolutionReport<'a>
+{
+    version: String,
+    release: Vec<u64>,
+    major: u64,
+    minor: u64,
+    patch: u64,
+    label: Option<&'a str>,
+    build: u32,
+    metadata: Option<&'a str>,
+    /// the version in every output format, `None` if it cannot be represented
+    formats: BTreeMap<&'static str, Option<String>>,
+    branch_type: &'a str,
+    branch: &'a str,
+    sha: &'a str,
+    commit_date: String,
+    basis: Option<&'a Basis>,
+    hint: VersionHint,
+    /// number of commits that incremented the version since the basis
+    increments: u32
+}
+
+impl Serialize for Solution
+{
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> std::result::Result<Ser::Ok, Ser::Error>
+    {
+        let version = self.version.as_ref();
+        let release = version.release();
+        let segment = |i: usize| release.get(i).copied().unwrap_or_default();
+
+        SolutionReport
+        {
+            version: self.text.clone(),
+            major: segment(0),
+            minor: segment(1),
+            patch: segment(2),
+            release: release.clone(),
+            label: version.label(),
+            build: version.build(),
+            metadata: version.metadata(),
+            formats: OutputFormat::ALL.iter().map(|f| (f.name(), f.format(version).ok())).collect(),
+            branch_type: &self.branch_type,
+            branch: &self.branch_name,
+            sha: &self.sha,
+            commit_date: CalDate::from_unix(self.commit_time.time, self.commit_time.offset_minutes).to_string(),
+            basis: self.basis.as_ref(),
+            hint: self.hint,
+            increments: self.commit_count
+        }.serialize(serializer)
     }
 }
 
 /// starting point for resolving a version from a git repository
-pub fn solve<O: ConsoleWriter + 'static>(output: &O, cwd: &Path, cfg: RawConfig, args: cli::Args) -> anyhow::Result<SemVersion>
+pub fn solve<O: ConsoleWriter + 'static>(output: &O, cwd: &Path, cfg: RawConfig, args: cli::Args) -> anyhow::Result<Solution>
 {
-    let cfg = cfg.parse()?;
-    let repo = args.git_dir.map_or_else(||git2::Repository::discover(cwd), |git_dir|git2::Repository::open(git_dir))?;
+    Ok(match cfg.scheme.clone().unwrap_or_default()
+    {
+        RawScheme::Semver =>
+        {
+            let scheme = cfg.semver_scheme();
+            solve_with_scheme(output, cwd, cfg, args, scheme)?.boxed()
+        },
+        RawScheme::Calver(ref format) => solve_with_scheme(output, cwd, cfg, args, CalVerScheme::new(format)?)?.boxed(),
+    })
+}
+
+/// resolves the version of every component of the config, by component name
+pub fn solve_all_components<O: ConsoleWriter + 'static>(output: &O, cwd: &Path, cfg: RawConfig, args: cli::Args) -> anyhow::Result<BTreeMap<String, Solution>>
+{
+    if cfg.components.is_empty()
+    {
+        bail!("no components configured");
+    }
+
+    cfg.components.keys()
+        .map(|name|
+        {
+            let args = cli::Args { component: Some(name.clone()), all_components: false, ..args.clone() };
+            let solution = solve(output, cwd, cfg.clone(), args).with_context(|| format!("failed to solve component {name}"))?;
+            Ok((name.clone(), solution))
+        })
+        .collect()
+}
+
+/// resolves a version using the given version scheme
+pub fn solve_with_scheme<O: ConsoleWriter + 'static, S: VersionScheme + Clone>(output: &O, cwd: &Path, mut cfg: RawConfig, args: cli::Args, scheme: S) -> anyhow::Result<Solution<S::Version>>
+{
+    if let Some(ref template) = args.template
+    {
+        check_template(template)?;
+    }
+
+    if let Some(ref component) = args.component
+    {
+        cfg = cfg.for_component(component)?;
+    }
+
+    if !args.include_paths.is_empty() || !args.exclude_paths.is_empty()
+    {
+        cfg.paths = Some(RawPathFilter { include: args.include_paths.clone(), exclude: args.exclude_paths.clone() });
+    }
+
+    let cfg = cfg.parse(scheme.clone())?;
+    let repo = args.git_dir.map_or_else(||git2::Repository::discover(cwd), git2::Repository::open)?;
+
+    let pr_target = |name: &str, tip: Oid| -> Result<Option<String>>
+    {
+        if config::pull_request_number(name).is_none()
+        {
+            return Ok(None);
+        }
+        if let Some(ref pr_target) = args.pr_target
+        {
+            return Ok(Some(pr_target.clone()));
+        }
+        let pr_target = detect_pr_target(&cfg, &repo, tip)?;
+        match pr_target
+        {
+            Some(ref pr_target) => output.user_line(LogLevel::Info, format!("pull request {name} merges into {pr_target}")),
+            None => output.user_line(LogLevel::Warning, format!("could not detect the target branch of pull request {name}, set it with --pr-target"))
+        }
+        Ok(pr_target)
+    };
+
+    let branch_map = |name: &str, target: Oid|
+    {
+        let Some(branch) = cfg.try_match_branch_with_pr_target(name, target, pr_target(name, target)?.as_deref())? else { bail!("could not resolve HEAD or current branch is not configured") };
+        Ok(branch)
+    };
 
     let branch_map_from_ref = |r: Reference|
     {
         let Some(name) = r.name() else { bail!("branch has no name") };
         let Some(target) = r.target() else { bail!("branch has no target") };
 
-        let name = cfg.reference_name_to_branch_name(name);
-
-        let Some(branch) = cfg.try_match_branch(name, target)? else { bail!("could not resolve HEAD or current branch is not configured") };
-        Ok(branch)
+        branch_map(cfg.reference_name_to_branch_name(name), target)
     };
 
     let branch =
@@ -270,15 +1101,22 @@ pub fn solve<O: ConsoleWriter + 'static>(output: &O, cwd: &Path, cfg: RawConfig,
     else if let Some(override_branch_name) = args.override_branch_name
     {
         let Some(head_id) = repo.head()?.target() else { bail!("HEAD does not point to a commit") };
-        let Some(m) = cfg.try_match_branch(&override_branch_name, head_id)? else { bail!("{override_branch_name} does not match any configured branch type") };
+        let Some(m) = cfg.try_match_branch_with_pr_target(&override_branch_name, head_id, pr_target(&override_branch_name, head_id)?.as_deref())? else { bail!("{override_branch_name} does not match any configured branch type") };
         m
     }
     else
     {
-        branch_map_from_ref(resolve_current_branch(output, &cfg, &repo)?)?
+        let (name, head_id) = resolve_current_branch(output, &cfg, &repo, |variable| std::env::var(variable).ok())?;
+        branch_map(&name, head_id)?
     };
 
-    
+    let branch_type = branch.config().r#type().to_string();
+    let branch_name = branch.name().to_string();
+    let tip = branch.tip();
+    let time = repo.find_commit(tip)?.time();
+
+    let refs = if args.use_local { BranchRefs::Local } else if args.use_remote { BranchRefs::Remote } else { BranchRefs::All };
+    let refs = RepoRefs::collect(output, &cfg, &repo, refs)?;
     let mut solver = BranchSolver::new(
         BranchSolveContext
             {
@@ -288,8 +1126,32 @@ pub fn solve<O: ConsoleWriter + 'static>(output: &O, cwd: &Path, cfg: RawConfig,
             output,
             &cfg,
             &repo,
+            &refs,
             branch
         )?;
-    Ok(solver.solve()?)
+    let (version, hint, explanation) = if args.command == Some(cli::GitCommand::Explain)
+    {
+        let explanation = solver.explain()?;
+        (explanation.version.clone(), explanation.hint, Some(explanation.to_string()))
+    }
+    else
+    {
+        let (version, hint) = solver.solve()?;
+        (version, hint, None)
+    };
+
+    Ok(Solution
+    {
+        text: scheme.format(&version),
+        version,
+        branch_type,
+        branch_name,
+        sha: tip.to_string(),
+        commit_time: CommitInfo { time: time.seconds(), offset_minutes: time.offset_minutes() },
+        commit_count: solver.commit_count,
+        basis: solver.basis,
+        hint,
+        explanation
+    })
 }
 
@@ -1,9 +1,11 @@
 #[cfg(test)]
 mod test
 {
+    use std::{cell::RefCell, collections::HashMap, path::PathBuf};
+
     use git2::Oid;
-    use verner_core::{output::ConsoleWriter, semver::SemVersion};
-    use crate::{config::{RawBranchConfig, RawConfig, RawTagConfig}, solve};
+    use verner_core::{output::ConsoleWriter, semver::{SemVerScheme, SemVersion, SemVersionInc}, VersionOp};
+    use crate::{config::{RawBranchConfig, RawConfig, RawScheme, RawTagConfig}, solve, solve_with_scheme};
 
     struct NullWriter;
     impl ConsoleWriter for NullWriter
@@ -13,28 +15,64 @@ mod test
     }
 
 
-    fn get_config() -> RawConfig
+    #[derive(Default)]
+    struct RecordingWriter(RefCell<Vec<String>>);
+    impl ConsoleWriter for RecordingWriter
     {
-        crate::config::preset_config(&crate::cli::ConfigPreset::Releaseflow).unwrap()
+        fn user_line<D: std::fmt::Display>(&self, level: verner_core::output::LogLevel, d: D)
+        {
+            if level == verner_core::output::LogLevel::Warning
+            {
+                self.0.borrow_mut().push(d.to_string());
+            }
+        }
+        fn output<D: std::fmt::Display>(&self, _d: D) {}
     }
 
 
-    fn solve_repo_version(repo_name: &str) -> anyhow::Result<SemVersion>
+    fn test_args(git_dir: PathBuf) -> crate::cli::Args
     {
-        let cfg = get_config();
-        let git_dir = std::env::current_dir()?.join(format!("../test_data/releaseflow/{repo_name}"));
-        let null_writer = NullWriter;
-
-        let ver = solve(&null_writer, &git_dir.clone(), cfg, crate::cli::Args
+        crate::cli::Args
         {
             config_preset: None,
             use_local: false,
+            use_remote: false,
             use_ref: None,
             override_branch_name: None,
-            git_dir: Some(git_dir)
-        })?;
+            pr_target: None,
+            git_dir: Some(git_dir),
+            format: None,
+            template: None,
+            output: None,
+            include_paths: vec![],
+            exclude_paths: vec![],
+            component: None,
+            all_components: false,
+            command: None
+        }
+    }
 
-        Ok(ver)
+
+    fn get_config() -> RawConfig
+    {
+        crate::config::preset_config(&crate::cli::ConfigPreset::Releaseflow).unwrap()
+    }
+
+
+    fn solve_repo_version(repo_name: &str) -> anyhow::Result<SemVersion>
+    {
+        solve_test_repo(&format!("releaseflow/{repo_name}"), get_config())
+    }
+
+    fn solve_test_repo(path: &str, cfg: RawConfig) -> anyhow::Result<SemVersion>
+    {
+        let git_dir = std::env::current_dir()?.join(format!("../test_data/{path}"));
+        let null_writer = NullWriter;
+
+        let scheme = cfg.semver_scheme();
+        let ver = solve_with_scheme(&null_writer, &git_dir.clone(), cfg, test_args(git_dir), scheme)?;
+
+        Ok(ver.version)
     }
 
     macro_rules! repo_test {
@@ -45,7 +83,8 @@ mod test
                 #[allow(non_snake_case)]
                 fn [<check_generated_version_$major _$minor _$patch _$tag _$build>]()
                 {
-                    let version = SemVersion::parse(&format!("{}.{}.{}-{}.{}", $major, $minor, $patch, $tag, $build)).unwrap();
+                    let mut version = SemVersion::parse(&format!("{}.{}.{}", $major, $minor, $patch)).unwrap().with_label(Some($tag.into()));
+                    version.inc(&SemVersionInc::Build($build));
                     let solved = solve_repo_version(&version.to_string()).unwrap();
                     assert!(version == solved, "expected: {} / actual: {}", version, solved);
                 }
@@ -77,8 +116,14 @@ mod test
             sources: vec![],
             v_next: None,
             max_depth: None,
+            first_parent: None,
+            mainline: None,
+            r#type: None,
+            priority: None,
+            conventional_commits: None,
+            directives: None
         };
-        let branch_config = branch_config.parse("test".into()).expect("config did not parse");
+        let branch_config = branch_config.parse("test".into(), SemVerScheme::default()).expect("config did not parse");
         let m = branch_config.try_match("test", Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap()).expect("error matching branch").expect("branch did not match");
         assert_eq!(m.tag(), Some("3e95d253526c821c9e5da1edfeb8d90f7d59aae4"));
     }
@@ -94,8 +139,14 @@ mod test
             sources: vec![],
             v_next: None,
             max_depth: None,
+            first_parent: None,
+            mainline: None,
+            r#type: None,
+            priority: None,
+            conventional_commits: None,
+            directives: None
         };
-        let branch_config = branch_config.parse("test".into()).expect("config did not parse");
+        let branch_config = branch_config.parse("test".into(), SemVerScheme::default()).expect("config did not parse");
         let m = branch_config.try_match("test", Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap()).expect("error matching branch").expect("branch did not match");
         assert_eq!(m.tag(), Some("3e95d253"));
     }
@@ -105,9 +156,10 @@ mod test
         let tag_config = RawTagConfig
         {
             regex: "test".into(),
-            version: "0.0.0-$hash".into()
+            version: "0.0.0-$hash".into(),
+            priority: None
         };
-        let tag_config = tag_config.parse("test".into()).expect("config did not parse");
+        let tag_config = tag_config.parse("test", SemVerScheme::default()).expect("config did not parse");
         let m = tag_config.try_match("test", Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap()).expect("error matching branch").expect("branch did not match");
         assert_eq!(m.version().label(), Some("3e95d253526c821c9e5da1edfeb8d90f7d59aae4"));
     }
@@ -117,10 +169,875 @@ mod test
         let tag_config = RawTagConfig
         {
             regex: "test".into(),
-            version: "0.0.0-$hash_short".into()
+            version: "0.0.0-$hash_short".into(),
+            priority: None
         };
-        let tag_config = tag_config.parse("test".into()).expect("config did not parse");
+        let tag_config = tag_config.parse("test", SemVerScheme::default()).expect("config did not parse");
         let m = tag_config.try_match("test", Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap()).expect("error matching branch").expect("branch did not match");
         assert_eq!(m.version().label(), Some("3e95d253"));
     }
-}
\ No newline at end of file
+    #[test]
+    fn invalid_base_version_fails_on_parse()
+    {
+        let branch_config = RawBranchConfig
+        {
+            regex: "^release/(?<major>\\d+)$".into(),
+            label: None,
+            base_version: Some("$major.x.0".into()),
+            tracked: vec![],
+            sources: vec![],
+            v_next: None,
+            max_depth: None,
+            first_parent: None,
+            mainline: None,
+            r#type: None,
+            priority: None,
+            conventional_commits: None,
+            directives: None
+        };
+        assert!(branch_config.parse("release".into(), SemVerScheme::default()).is_err());
+    }
+    #[test]
+    fn invalid_tag_version_fails_on_parse()
+    {
+        let tag_config = RawTagConfig
+        {
+            regex: "^v(?<major>\\d+)$".into(),
+            version: "$major.0.0-$unknown".into(),
+            priority: None
+        };
+        assert!(tag_config.parse("test", SemVerScheme::default()).is_err());
+    }
+    #[test]
+    fn preset_roundtrips_through_yaml()
+    {
+        let yaml = serde_yaml::to_string(&get_config()).unwrap();
+        let cfg: RawConfig = serde_yaml::from_str(&yaml).unwrap();
+        let cfg = cfg.parse(SemVerScheme::default()).unwrap();
+        let main = cfg.find_branches("main").next().unwrap();
+        assert!(matches!(main.v_next(), Some(SemVersionInc::Minor(1))));
+    }
+    #[test]
+    fn select_scheme_in_config()
+    {
+        let cfg: RawConfig = serde_yaml::from_str("scheme: semver\nbranches: {}").unwrap();
+        assert!(matches!(cfg.scheme, Some(RawScheme::Semver)));
+        assert!(serde_yaml::from_str::<RawConfig>("scheme: unknown\nbranches: {}").is_err());
+
+        let cfg: RawConfig = serde_yaml::from_str("scheme: !calver YYYY.0M.patch\nbranches: {}").unwrap();
+        assert!(matches!(cfg.scheme, Some(RawScheme::Calver(ref format)) if format == "YYYY.0M.patch"));
+    }
+    #[test]
+    fn calver_counter_resets_on_date_change()
+    {
+        let cfg = RawConfig
+        {
+            scheme: Some(RawScheme::Calver("YYYY.0M.patch".into())),
+            tracked_remotes: vec![],
+            label_precedence: vec![],
+            paths: None,
+            components: HashMap::new(),
+            ci_branch_variables: None,
+            tags: HashMap::from([
+                ("release".into(), RawTagConfig
+                {
+                    regex: "^v(?<version>.+)$".into(),
+                    version: "$version".into(),
+                    priority: None
+                })
+            ]),
+            branches: HashMap::from([
+                ("main".into(), RawBranchConfig
+                {
+                    regex: "^main$".into(),
+                    label: None,
+                    base_version: None,
+                    tracked: vec![],
+                    sources: vec![],
+                    v_next: None,
+                    max_depth: None,
+                    first_parent: None,
+                    mainline: None,
+                    r#type: None,
+                    priority: None,
+                    conventional_commits: None,
+                    directives: None
+                })
+            ])
+        };
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/calver/2024.05.1");
+
+        let ver = solve(&NullWriter, &git_dir.clone(), cfg, test_args(git_dir)).unwrap();
+
+        assert_eq!(ver.version.to_string(), "2024.05.1");
+        assert_eq!(ver.version.release(), [2024, 5, 1]);
+        assert_eq!(ver.text, "2024.05.1");
+        assert_eq!(ver.variable("version").as_deref(), Some("2024.05.1"));
+        assert_eq!(ver.variable("commit_date").as_deref(), Some("2024-05-03"));
+        assert_eq!(ver.variable("tag").as_deref(), Some("v2024.04.0"));
+    }
+    #[test]
+    fn calver_conventional_commits_need_increments()
+    {
+        let calver = || verner_core::calver::CalVerScheme::new("YYYY.0M.patch").unwrap();
+        let cfg: RawConfig = serde_yaml::from_str("branches:\n  main:\n    regex: ^main$\n    conventional_commits: {}").unwrap();
+        let err = cfg.parse(calver()).err().unwrap();
+        assert!(format!("{err:#}").contains("no default increment for breaking changes, set `breaking`"), "{err:#}");
+
+        let cfg: RawConfig = serde_yaml::from_str("branches:\n  main:\n    regex: ^main$\n    conventional_commits: { breaking: !Counter 1, types: { feat: !Counter 1 } }").unwrap();
+        assert!(cfg.parse(calver()).is_ok());
+    }
+    #[test]
+    fn calver_directives_need_increments()
+    {
+        let calver = || verner_core::calver::CalVerScheme::new("YYYY.0M.patch").unwrap();
+        let cfg: RawConfig = serde_yaml::from_str("branches:\n  main:\n    regex: ^main$\n    directives: {}").unwrap();
+        let err = cfg.parse(calver()).err().unwrap();
+        assert!(format!("{err:#}").contains("no default increments for directives, set `increments`"), "{err:#}");
+
+        let cfg: RawConfig = serde_yaml::from_str("branches:\n  main:\n    regex: ^main$\n    directives: { increments: { day: !Counter 1 } }").unwrap();
+        assert!(cfg.parse(calver()).is_ok());
+    }
+    #[test]
+    fn unknown_increments_are_rejected()
+    {
+        assert!(serde_yaml::from_str::<RawConfig>("branches:\n  main:\n    regex: ^main$\n    v_next: !Minr 1").is_err());
+
+        let cfg: RawConfig = serde_yaml::from_str("branches:\n  main:\n    regex: ^main$\n    v_next: !Minor 1").unwrap();
+        let err = cfg.parse(verner_core::calver::CalVerScheme::new("YYYY.0M.patch").unwrap()).err().unwrap();
+        assert!(format!("{err:#}").contains("invalid v_next of branch config main: calver only has the counter increment"), "{err:#}");
+    }
+    #[test]
+    fn render_label_template_variables()
+    {
+        let branch_config = RawBranchConfig
+        {
+            regex: "^feat/(?<name>[^/]+)(?:/(?<sub>.+))?$".into(),
+            label: Some("$branch_type-$name$sub.$sha_short".into()),
+            base_version: None,
+            tracked: vec![],
+            sources: vec![],
+            v_next: None,
+            max_depth: None,
+            first_parent: None,
+            mainline: None,
+            r#type: None,
+            priority: None,
+            conventional_commits: None,
+            directives: None
+        };
+        let branch_config = branch_config.parse("feature".into(), SemVerScheme::default()).expect("config did not parse");
+        let m = branch_config.try_match("feat/abc", Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap()).expect("error matching branch").expect("branch did not match");
+        assert_eq!(m.tag(), Some("feature-abc.3e95d253"));
+        assert_eq!(m.name(), "feat/abc");
+    }
+    #[test]
+    fn unknown_label_variable_fails_on_parse()
+    {
+        let branch_config = RawBranchConfig
+        {
+            regex: "^feat/(?<name>.+)$".into(),
+            label: Some("${nmae}".into()),
+            base_version: None,
+            tracked: vec![],
+            sources: vec![],
+            v_next: None,
+            max_depth: None,
+            first_parent: None,
+            mainline: None,
+            r#type: None,
+            priority: None,
+            conventional_commits: None,
+            directives: None
+        };
+        assert!(branch_config.parse("feature".into(), SemVerScheme::default()).is_err());
+    }
+    #[test]
+    fn render_tag_version_with_indexed_group()
+    {
+        let tag_config = RawTagConfig
+        {
+            regex: r"^v(\d+)\.(\d+)$".into(),
+            version: "${1}.${2}.0".into(),
+            priority: None
+        };
+        let tag_config = tag_config.parse("test", SemVerScheme::default()).expect("config did not parse");
+        let m = tag_config.try_match("v2.5", Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap()).expect("error matching tag").expect("tag did not match");
+        assert_eq!(m.version().to_string(), "2.5.0");
+    }
+    #[test]
+    fn json_output_describes_solution()
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/releaseflow/1.0.0-rc.1");
+        let solution = solve(&NullWriter, &git_dir.clone(), get_config(), crate::cli::Args
+        {
+            output: Some(crate::cli::OutputMode::Json),
+            ..test_args(git_dir)
+        }).unwrap();
+
+        let json = serde_json::to_value(&solution).unwrap();
+        assert_eq!(json["version"], "1.0.0-rc.1");
+        assert_eq!(json["label"], "rc");
+        assert_eq!(json["build"], 1);
+        assert_eq!(json["formats"]["pep440"], "1.0.0rc1");
+        assert_eq!(json["branch_type"], "release");
+        assert_eq!(json["sha"], solution.sha.as_str());
+        assert_eq!(json["basis"]["kind"], "base_version");
+        assert_eq!(json["hint"], "derived");
+        assert_eq!(json["increments"], 1);
+    }
+    #[test]
+    fn solution_provides_all_template_variables()
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/releaseflow/1.0.0-rc.1");
+        let solution = solve(&NullWriter, &git_dir.clone(), get_config(), test_args(git_dir)).unwrap();
+        for name in crate::SOLUTION_VARIABLES
+        {
+            assert!(solution.variable(name).is_some(), "{name}");
+        }
+    }
+    #[test]
+    fn unknown_template_variable_fails_before_solving()
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/does-not-exist");
+        let args = crate::cli::Args { template: Some(verner_core::template::Template::parse("{major}.{mnior}").unwrap()), ..test_args(git_dir.clone()) };
+        let err = solve(&NullWriter, &git_dir, get_config(), args).err().unwrap();
+        assert!(err.to_string().starts_with("unknown variable 'mnior' in template '{major}.{mnior}'"), "{err}");
+    }
+    #[test]
+    fn only_json_output_conflicts_with_format()
+    {
+        let parse = |args: &[&str]| <crate::cli::Args as clap::Parser>::try_parse_from([&["git"], args].concat()).unwrap();
+        assert!(parse(&["--output", "text", "--format", "pep440"]).check_output().is_ok());
+        assert!(parse(&["--template", "{major}"]).check_output().is_ok());
+        assert!(parse(&["--output", "json"]).check_output().is_ok());
+        assert!(parse(&["--output", "json", "--format", "pep440"]).check_output().is_err());
+        assert!(parse(&["--output", "json", "--template", "{major}"]).check_output().is_err());
+        assert!(parse(&["--output", "json", "explain"]).check_output().is_err());
+        assert!(parse(&["explain"]).check_output().is_ok());
+    }
+    #[test]
+    fn explain_includes_nested_source_solve()
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/releaseflow/1.1.0-feat-depth1.1");
+        let solution = solve(&NullWriter, &git_dir.clone(), get_config(), crate::cli::Args
+        {
+            command: Some(crate::cli::GitCommand::Explain),
+            ..test_args(git_dir)
+        }).unwrap();
+
+        assert_eq!(solution.version.to_string(), "1.1.0-feat-depth1.1");
+        let explanation = solution.explanation.expect("no explanation recorded");
+        let lines: Vec<&str> = explanation.lines().collect();
+        assert_eq!(lines[0], "1.1.0-feat-depth1.1 (derived)");
+        assert!(lines[2].contains("branch root on main (main): soft basis 1.1.0-SNAPSHOT"), "{explanation}");
+        assert_eq!(lines[3], "    1.1.0-SNAPSHOT.1 (derived)");
+        assert!(lines.iter().any(|l| l.starts_with("      ") && l.contains("root of tracked branch")), "{explanation}");
+    }
+    #[test]
+    fn annotated_tag_is_peeled_to_commit()
+    {
+        assert_eq!(solve_test_repo("tags/annotated", get_config()).unwrap().to_string(), "1.0.1-rc.1");
+    }
+    #[test]
+    fn tag_of_tag_is_peeled_to_commit()
+    {
+        assert_eq!(solve_test_repo("tags/tag-of-tag", get_config()).unwrap().to_string(), "1.0.0");
+    }
+    #[test]
+    fn invalid_version_tags_are_ignored()
+    {
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/tags/leading-zero");
+        assert_eq!(solve(&writer, &git_dir.clone(), get_config(), test_args(git_dir)).unwrap().version.to_string(), "1.1.0-SNAPSHOT.1");
+        let warnings = writer.0.borrow();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("ignoring tag v1.02.0: "), "{}", warnings[0]);
+        assert!(warnings[0].contains("leading zeros"), "{}", warnings[0]);
+    }
+    #[test]
+    fn commits_dated_before_the_tag_are_counted()
+    {
+        assert_eq!(solve_test_repo("tags/backdated-merge", get_config()).unwrap().to_string(), "1.1.0-SNAPSHOT.3");
+    }
+    #[test]
+    fn lightweight_and_annotated_tag_on_same_commit()
+    {
+        let mut cfg = get_config();
+        cfg.tags.insert("legacy".into(), RawTagConfig
+        {
+            regex: r"^release-(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)$".into(),
+            version: "$major.$minor.$patch".into(),
+            priority: None
+        });
+        assert_eq!(solve_test_repo("tags/mixed", cfg).unwrap().to_string(), "1.0.1-rc.1");
+    }
+    #[test]
+    fn tag_matching_several_configs_uses_highest_priority()
+    {
+        for (priority, expected) in [(1, "1.0.9"), (-1, "1.0.0")]
+        {
+            let mut cfg = get_config();
+            cfg.tags.insert("patched".into(), RawTagConfig
+            {
+                regex: r"^v(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)$".into(),
+                version: "$major.$minor.9".into(),
+                priority: Some(priority)
+            });
+            assert_eq!(solve_test_repo("tags/tag-of-tag", cfg).unwrap().to_string(), expected);
+        }
+    }
+    fn rc_and_release_config(pre_release_priority: Option<i32>) -> RawConfig
+    {
+        let mut cfg = get_config();
+        cfg.tags.insert("pre-release".into(), RawTagConfig
+        {
+            regex: r"^v(?<version>\d+\.\d+\.\d+-.+)$".into(),
+            version: "$version".into(),
+            priority: pre_release_priority
+        });
+        cfg
+    }
+    fn solve_rc_and_release(writer: &RecordingWriter, cfg: RawConfig) -> String
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/tags/rc-and-release");
+        solve(writer, &git_dir.clone(), cfg, test_args(git_dir)).unwrap().version.to_string()
+    }
+    #[test]
+    fn several_tags_on_commit_use_highest_version()
+    {
+        let writer = RecordingWriter::default();
+        assert_eq!(solve_rc_and_release(&writer, rc_and_release_config(None)), "1.0.0");
+        let warnings = writer.0.borrow();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].ends_with("using v1.0.0 and ignoring v1.0.0-rc.3"), "{}", warnings[0]);
+    }
+    #[test]
+    fn several_tags_on_commit_use_highest_priority()
+    {
+        let writer = RecordingWriter::default();
+        assert_eq!(solve_rc_and_release(&writer, rc_and_release_config(Some(1))), "1.0.0-rc.3");
+    }
+    #[test]
+    fn several_tags_on_commit_use_configured_label_precedence()
+    {
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/tags/labels");
+        let mut cfg = rc_and_release_config(None);
+        cfg.label_precedence = vec!["beta".into(), "alpha".into()];
+        assert_eq!(solve(&writer, &git_dir.clone(), cfg, test_args(git_dir)).unwrap().version.to_string(), "1.0.0-alpha");
+        let warnings = writer.0.borrow();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].ends_with("using v1.0.0-alpha and ignoring v1.0.0-beta"), "{}", warnings[0]);
+    }
+    #[test]
+    fn configured_label_precedence_is_used_when_solving()
+    {
+        assert_eq!(solve_test_repo("tags/labels", rc_and_release_config(None)).unwrap().to_string(), "1.0.0-beta");
+        let mut cfg = rc_and_release_config(None);
+        cfg.label_precedence = vec!["beta".into(), "alpha".into()];
+        assert_eq!(solve_test_repo("tags/labels", cfg).unwrap().to_string(), "1.0.0-alpha");
+    }
+    fn overlapping_branches_config(hotfix_priority: Option<i32>) -> RawConfig
+    {
+        serde_yaml::from_str(&format!(r"
+branches:
+  release:
+    regex: ^release/(?<major>\d+)\.(?<minor>\d+)$
+    base_version: $major.$minor.0
+    label: rc
+  release-hotfix:
+    type: release
+    regex: ^release/(?<major>\d+)\.(?<minor>\d+)(?:-hotfix)?$
+    base_version: $major.$minor.1
+    label: hotfix
+    {}
+", hotfix_priority.map(|p| format!("priority: {p}")).unwrap_or_default())).unwrap()
+    }
+    #[test]
+    fn overlapping_branch_configs_fail_without_priority()
+    {
+        let cfg = overlapping_branches_config(None).parse(SemVerScheme::default()).unwrap();
+        let id = Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap();
+        let err = cfg.try_match_branch("release/1.2", id).err().expect("ambiguous match did not fail").to_string();
+        assert!(err.contains("release, release-hotfix"), "{err}");
+        assert!(cfg.try_match_branch("release/1.2-hotfix", id).unwrap().is_some());
+    }
+    #[test]
+    fn branch_priority_resolves_overlap()
+    {
+        let cfg = overlapping_branches_config(Some(1)).parse(SemVerScheme::default()).unwrap();
+        let id = Oid::from_str("3e95d253526c821c9e5da1edfeb8d90f7d59aae4").unwrap();
+        let m = cfg.try_match_branch("release/1.2", id).unwrap().unwrap();
+        assert_eq!(m.config().name(), "release-hotfix");
+        assert_eq!(m.config().r#type(), "release");
+        assert_eq!(cfg.find_branches("release").count(), 2);
+        assert_eq!(cfg.find_type_branch_config_for("release/1.2", id, "release").unwrap().unwrap().tag(), Some("hotfix"));
+    }
+    #[test]
+    fn branch_configs_with_the_same_regex_and_priority_are_rejected()
+    {
+        let mut cfg = overlapping_branches_config(None);
+        let release = cfg.branches["release"].regex.clone();
+        cfg.branches.get_mut("release-hotfix").unwrap().regex = release;
+        let err = cfg.parse(SemVerScheme::default()).err().expect("overlap not detected");
+        assert_eq!(err.to_string(), "branch configs release and release-hotfix have the same regex and priority 0, set a priority to choose one");
+    }
+    #[test]
+    fn overlapping_source_configs_skip_the_source()
+    {
+        let mut cfg = get_config();
+        let mut release = cfg.branches["release"].clone();
+        release.r#type = Some("release".into());
+        release.regex = r"^release/(?<major>\d+)\.(?<minor>\d+)\.x$".into();
+        cfg.branches.insert("release-x".into(), release);
+
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/sources/nearest");
+        let solution = solve(&writer, &git_dir, cfg, test_args(git_dir.clone())).unwrap();
+        assert!(matches!(solution.basis, Some(crate::Basis::Source { ref branch, .. }) if branch == "main"));
+        assert!(writer.0.borrow().iter().any(|w| w == "ignoring source release/1.1.x: branch release/1.1.x matches several branch configs with priority 0: release, release-x, set a priority to choose one"), "{:?}", writer.0.borrow());
+    }
+    /// a `v<major>.<minor>.<patch>` release tag and a `main` branch with vNext minor, tests add the keys they test
+    fn base_config() -> RawConfig
+    {
+        serde_yaml::from_str(r"
+tags:
+  release:
+    regex: ^v(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)$
+    version: $major.$minor.$patch
+branches:
+  main:
+    regex: ^main$
+    v_next: !Minor 1
+").unwrap()
+    }
+    fn main_branch(cfg: &mut RawConfig) -> &mut RawBranchConfig
+    {
+        cfg.branches.get_mut("main").unwrap()
+    }
+    fn conventional_config() -> RawConfig
+    {
+        let mut cfg = base_config();
+        main_branch(&mut cfg).conventional_commits = Some(serde_yaml::from_str("{}").unwrap());
+        cfg
+    }
+    macro_rules! conventional_test {
+        ($name:ident, $version:expr) =>
+        {
+            #[test]
+            fn $name()
+            {
+                assert_eq!(solve_test_repo(concat!("conventional/", $version), conventional_config()).unwrap().to_string(), $version);
+            }
+        };
+    }
+    conventional_test!(conventional_fix_bumps_patch, "1.0.1-2");
+    conventional_test!(conventional_highest_bump_wins, "1.1.0-3");
+    conventional_test!(conventional_breaking_footer_bumps_major, "2.0.0-2");
+    conventional_test!(conventional_without_bump_uses_v_next, "1.1.0-1");
+    conventional_test!(conventional_without_tag_bumps_default_basis, "0.1.0-2");
+
+    fn directives_config() -> RawConfig
+    {
+        let mut cfg = base_config();
+        main_branch(&mut cfg).directives = Some(serde_yaml::from_str("{}").unwrap());
+        cfg
+    }
+    macro_rules! directives_test {
+        ($name:ident, $version:expr) =>
+        {
+            #[test]
+            fn $name()
+            {
+                assert_eq!(solve_test_repo(concat!("directives/", $version), directives_config()).unwrap().to_string(), $version);
+            }
+        };
+    }
+    directives_test!(skip_directive_ignores_commit, "1.1.0-1");
+    directives_test!(bump_directive_replaces_v_next, "2.0.0-2");
+    directives_test!(release_as_directive_sets_version, "3.0.0-1");
+
+    #[test]
+    fn release_as_directive_is_bumped_by_later_commits()
+    {
+        let mut cfg = directives_config();
+        main_branch(&mut cfg).conventional_commits = Some(serde_yaml::from_str("{}").unwrap());
+        assert_eq!(solve_test_repo("directives/3.1.0-1", cfg).unwrap().to_string(), "3.1.0-1");
+    }
+
+    #[test]
+    fn unknown_bump_directive_fails()
+    {
+        let err = solve_test_repo("directives/unknown-increment", directives_config()).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown increment 'huge'"), "{err:#}");
+    }
+    #[test]
+    fn directive_without_capture_group_fails_on_parse()
+    {
+        let cfg: RawConfig = serde_yaml::from_str(r"
+branches:
+  main:
+    regex: ^main$
+    directives:
+      release_as: 'Release-As: (\S+)'
+").unwrap();
+        assert!(cfg.parse(SemVerScheme::default()).is_err());
+    }
+    #[test]
+    fn release_as_directive_without_version_fails()
+    {
+        let cfg: RawConfig = serde_yaml::from_str(r"
+branches:
+  main:
+    regex: ^main$
+    directives:
+      release_as: 'Release-As:(?: (?<version>\S+))?'
+").unwrap();
+        let cfg = cfg.parse(SemVerScheme::default()).unwrap();
+        let directives = cfg.find_branches("main").next().unwrap().directives().unwrap();
+        assert!(matches!(directives.find("Release-As: 2.0.0"), Ok(Some(crate::config::Directive::ReleaseAs("2.0.0")))));
+        let err = directives.find("Release-As:").err().expect("missing version not reported");
+        assert_eq!(err.to_string(), "release_as directive matched 'Release-As:' without the group 'version'");
+    }
+    fn solve_monorepo(paths: Option<&str>, include: &[&str], exclude: &[&str]) -> String
+    {
+        let mut cfg = base_config();
+        cfg.paths = paths.map(|paths| serde_yaml::from_str(paths).unwrap());
+
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/paths/monorepo");
+        solve(&NullWriter, &git_dir.clone(), cfg, crate::cli::Args
+        {
+            include_paths: include.iter().map(|p| p.to_string()).collect(),
+            exclude_paths: exclude.iter().map(|p| p.to_string()).collect(),
+            ..test_args(git_dir)
+        }).unwrap().version.to_string()
+    }
+    #[test]
+    fn paths_filter_counts_only_matching_commits()
+    {
+        assert_eq!(solve_monorepo(None, &[], &[]), "1.1.0-4");
+        assert_eq!(solve_monorepo(Some("include: [api]"), &[], &[]), "1.1.0-2");
+        assert_eq!(solve_monorepo(Some("exclude: [api]"), &[], &[]), "1.1.0-2");
+        assert_eq!(solve_monorepo(Some("{ include: [api], exclude: [api/lib.rs] }"), &[], &[]), "1.1.0-1");
+        assert_eq!(solve_monorepo(Some("include: [web/*.css]"), &[], &[]), "1.1.0-1");
+    }
+    #[test]
+    fn paths_on_cli_override_config()
+    {
+        assert_eq!(solve_monorepo(Some("include: [api]"), &["web"], &[]), "1.1.0-2");
+        assert_eq!(solve_monorepo(Some("include: [api]"), &[], &["web"]), "1.1.0-2");
+        assert_eq!(solve_monorepo(None, &["docs"], &[]), "1.0.0");
+    }
+    fn components_config() -> RawConfig
+    {
+        let mut cfg = base_config();
+        cfg.components = serde_yaml::from_str(r"
+api:
+  tags:
+    release:
+      regex: ^api/v(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)$
+      version: $major.$minor.$patch
+  paths:
+    include: [api]
+web:
+  tags:
+    release:
+      regex: ^web/v(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)$
+      version: $major.$minor.$patch
+  paths:
+    include: [web]
+  branches:
+    main:
+      regex: ^main$
+      v_next: !Major 1
+").unwrap();
+        cfg
+    }
+    fn solve_component(component: Option<&str>) -> anyhow::Result<String>
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/components/monorepo");
+        let args = crate::cli::Args { component: component.map(str::to_string), ..test_args(git_dir.clone()) };
+        Ok(solve(&NullWriter, &git_dir, components_config(), args)?.version.to_string())
+    }
+    #[test]
+    fn component_uses_its_own_tags_paths_and_branches()
+    {
+        assert_eq!(solve_component(Some("api")).unwrap(), "1.1.0-2");
+        assert_eq!(solve_component(Some("web")).unwrap(), "3.0.0-1");
+        // without a component, only the shared config applies and no tag matches
+        assert_eq!(solve_component(None).unwrap(), "0.0.0-4");
+    }
+    #[test]
+    fn unknown_component_fails()
+    {
+        let err = solve_component(Some("docs")).unwrap_err();
+        assert_eq!(err.to_string(), "unknown component 'docs', expected one of: api, web");
+    }
+    #[test]
+    fn all_components_are_solved()
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/components/monorepo");
+        let solutions = crate::solve_all_components(&NullWriter, &git_dir, components_config(), test_args(git_dir.clone())).unwrap();
+        let versions: Vec<(&str, String)> = solutions.iter().map(|(name, s)| (name.as_str(), s.version.to_string())).collect();
+        assert_eq!(versions, vec![("api", "1.1.0-2".to_string()), ("web", "3.0.0-1".to_string())]);
+
+        assert!(crate::solve_all_components(&NullWriter, &git_dir, RawConfig { components: HashMap::new(), ..components_config() }, test_args(git_dir.clone())).is_err());
+    }
+    fn first_parent_config(first_parent: bool) -> RawConfig
+    {
+        let mut cfg = base_config();
+        main_branch(&mut cfg).first_parent = Some(first_parent);
+        cfg
+    }
+    #[test]
+    fn first_parent_counts_merges_only()
+    {
+        assert_eq!(solve_test_repo("first-parent/merged-feature", first_parent_config(true)).unwrap().to_string(), "1.1.0-2");
+    }
+    #[test]
+    fn without_first_parent_side_branch_tags_are_found()
+    {
+        assert_eq!(solve_test_repo("first-parent/merged-feature", first_parent_config(false)).unwrap().to_string(), "1.6.0-3");
+    }
+    fn solve_mainline(repo: &str, reference: &str, conventional_commits: bool) -> String
+    {
+        let mut cfg = base_config();
+        let main = main_branch(&mut cfg);
+        main.regex = "^(main|mainline/.*)$".into();
+        main.mainline = Some(serde_yaml::from_str("!Patch 1").unwrap());
+        if conventional_commits
+        {
+            main.conventional_commits = Some(serde_yaml::from_str("{}").unwrap());
+        }
+
+        let git_dir = std::env::current_dir().unwrap().join(format!("../test_data/mainline/{repo}"));
+        solve(&NullWriter, &git_dir.clone(), cfg, crate::cli::Args { use_ref: Some(reference.into()), ..test_args(git_dir) }).unwrap().version.to_string()
+    }
+    #[test]
+    fn mainline_increments_every_merge()
+    {
+        assert_eq!(solve_mainline("merges", "refs/heads/mainline/after-fix", false), "1.4.1");
+        assert_eq!(solve_mainline("merges", "refs/heads/mainline/after-feat", false), "1.4.2");
+        assert_eq!(solve_mainline("merges", "refs/heads/main", false), "1.4.2-1");
+    }
+    #[test]
+    fn mainline_uses_increments_of_merged_commits()
+    {
+        assert_eq!(solve_mainline("merges", "refs/heads/mainline/after-fix", true), "1.4.1");
+        assert_eq!(solve_mainline("merges", "refs/heads/mainline/after-feat", true), "1.5.0");
+        assert_eq!(solve_mainline("merges", "refs/heads/main", true), "1.5.0-1");
+    }
+    #[test]
+    fn mainline_direct_commits_are_build_increments()
+    {
+        assert_eq!(solve_mainline("mixed", "refs/heads/mainline/after-fix", false), "1.4.1");
+        assert_eq!(solve_mainline("mixed", "refs/heads/mainline/direct", false), "1.4.1-2");
+        assert_eq!(solve_mainline("mixed", "refs/heads/main", false), "1.4.2");
+        // the fix committed directly does not bump, the merged feature does
+        assert_eq!(solve_mainline("mixed", "refs/heads/mainline/direct", true), "1.4.1-2");
+        assert_eq!(solve_mainline("mixed", "refs/heads/main", true), "1.5.0");
+    }
+    #[test]
+    fn pull_request_refs_are_branch_names()
+    {
+        let cfg = get_config().parse(SemVerScheme::default()).unwrap();
+        assert_eq!(cfg.reference_name_to_branch_name("refs/pull/123/merge"), "pull/123/merge");
+        assert_eq!(cfg.reference_name_to_branch_name("refs/remotes/pull/123/merge"), "pull/123/merge");
+        assert_eq!(cfg.reference_name_to_branch_name("refs/remotes/origin/pull/123/head"), "pull/123/head");
+        assert_eq!(cfg.reference_name_to_branch_name("refs/merge-requests/45/head"), "merge-requests/45/head");
+        assert_eq!(cfg.reference_name_to_branch_name("refs/pull/abc/merge"), "refs/pull/abc/merge");
+    }
+    fn solve_pull_request(reference: &str, pr_target: Option<&str>) -> String
+    {
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/pull-requests/into-release");
+        let args = crate::cli::Args { use_ref: Some(reference.into()), pr_target: pr_target.map(str::to_string), ..test_args(git_dir.clone()) };
+        solve(&NullWriter, &git_dir, get_config(), args).unwrap().version.to_string()
+    }
+    #[test]
+    fn pull_request_merge_ref_uses_first_parent_as_target()
+    {
+        assert_eq!(solve_pull_request("refs/pull/7/merge", None), "1.0.0-pr-7.2");
+    }
+    #[test]
+    fn pull_request_target_selects_the_source()
+    {
+        assert_eq!(solve_pull_request("refs/merge-requests/45/head", Some("release/1.0.x")), "1.0.0-pr-45.1");
+        assert_eq!(solve_pull_request("refs/merge-requests/45/head", Some("main")), "1.0.0-pr-45.2");
+    }
+    #[test]
+    fn pull_request_variables_in_label()
+    {
+        let mut cfg = get_config();
+        cfg.branches.get_mut("pull-request").unwrap().label = Some("pr-$pr-into-$target_branch".into());
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/pull-requests/into-release");
+        let args = crate::cli::Args { use_ref: Some("refs/merge-requests/45/head".into()), pr_target: Some("main".into()), ..test_args(git_dir.clone()) };
+        let solution = solve(&NullWriter, &git_dir, cfg, args).unwrap();
+        assert_eq!(solution.version.to_string(), "1.0.0-pr-45-into-main.2");
+    }
+    fn resolve_ci_branch(cfg: RawConfig, variables: &[(&str, &str)]) -> anyhow::Result<String>
+    {
+        let cfg = cfg.parse(SemVerScheme::default()).unwrap();
+        let repo = git2::Repository::open(std::env::current_dir().unwrap().join("../test_data/ci/detached")).unwrap();
+        let env = |name: &str| variables.iter().find(|(n, _)| *n == name).map(|(_, value)| value.to_string());
+        Ok(crate::resolve_current_branch(&NullWriter, &cfg, &repo, env)?.0)
+    }
+    #[test]
+    fn detached_head_uses_ci_variables()
+    {
+        assert_eq!(resolve_ci_branch(get_config(), &[("GITHUB_REF", "refs/heads/release/1.0.x")]).unwrap(), "release/1.0.x");
+        assert_eq!(resolve_ci_branch(get_config(), &[("BUILD_SOURCEBRANCH", "refs/tags/v1.0.0"), ("CI_COMMIT_REF_NAME", "main")]).unwrap(), "main");
+        assert_eq!(resolve_ci_branch(get_config(), &[("GITHUB_REF", "refs/heads/main"), ("BRANCH_NAME", "release/1.0.x")]).unwrap(), "main");
+        assert!(resolve_ci_branch(get_config(), &[]).is_err());
+    }
+    #[test]
+    fn ci_variables_are_configurable()
+    {
+        let cfg = RawConfig { ci_branch_variables: Some(vec!["MY_BRANCH".into(), "GITHUB_REF".into()]), ..get_config() };
+        assert_eq!(resolve_ci_branch(cfg.clone(), &[("GITHUB_REF", "refs/heads/main"), ("MY_BRANCH", "release/1.0.x")]).unwrap(), "release/1.0.x");
+        assert!(resolve_ci_branch(RawConfig { ci_branch_variables: Some(vec![]), ..cfg }, &[("GITHUB_REF", "refs/heads/main")]).is_err());
+    }
+    fn solve_remotes(writer: &RecordingWriter, repo: &str, use_local: bool, use_remote: bool) -> String
+    {
+        let git_dir = std::env::current_dir().unwrap().join(format!("../test_data/remotes/{repo}"));
+        let args = crate::cli::Args { use_local, use_remote, ..test_args(git_dir.clone()) };
+        solve(writer, &git_dir, get_config(), args).unwrap().version.to_string()
+    }
+    #[test]
+    fn local_and_remote_branch_selection()
+    {
+        let writer = RecordingWriter::default();
+        assert_eq!(solve_remotes(&writer, "remote-ahead", true, false), "0.1.0-feat-remote.2");
+        assert_eq!(solve_remotes(&writer, "remote-ahead", false, true), "0.1.0-feat-remote.1");
+        assert!(writer.0.borrow().is_empty());
+    }
+    #[test]
+    fn more_advanced_of_local_and_remote_branch_is_used()
+    {
+        let writer = RecordingWriter::default();
+        assert_eq!(solve_remotes(&writer, "remote-ahead", false, false), "0.1.0-feat-remote.1");
+        assert!(writer.0.borrow().is_empty());
+    }
+    #[test]
+    fn diverged_local_and_remote_branch_warns()
+    {
+        let writer = RecordingWriter::default();
+        assert_eq!(solve_remotes(&writer, "diverged", false, false), "0.1.0-feat-diverged.2");
+        assert_eq!(writer.0.borrow().as_slice(), ["local and remote branch main diverged, using the local branch"]);
+        assert_eq!(solve_remotes(&RecordingWriter::default(), "diverged", false, true), "0.1.0-feat-diverged.1");
+    }
+    #[test]
+    fn nearest_source_is_used()
+    {
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/sources/nearest");
+        let solution = solve(&writer, &git_dir, get_config(), test_args(git_dir.clone())).unwrap();
+        assert_eq!(solution.version.to_string(), "1.1.0-feat-nearest.1");
+        assert!(matches!(solution.basis, Some(crate::Basis::Source { ref branch, .. }) if branch == "release/1.1.x"));
+        assert!(writer.0.borrow().is_empty());
+    }
+    #[test]
+    fn sources_without_common_history_are_ignored()
+    {
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/sources/unrelated");
+        let solution = solve(&writer, &git_dir, get_config(), test_args(git_dir.clone())).unwrap();
+        assert_eq!(solution.version.to_string(), "0.1.0-feat-unrelated.1");
+        assert!(matches!(solution.basis, Some(crate::Basis::Source { ref branch, .. }) if branch == "main"));
+    }
+    #[test]
+    fn equally_near_sources_are_reported()
+    {
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/releaseflow/1.0.0-feat-on-root");
+        solve(&writer, &git_dir, get_config(), test_args(git_dir.clone())).unwrap();
+        assert_eq!(writer.0.borrow().as_slice(), ["sources of feature/on-root are equally near but differ, using main (1.0.0-SNAPSHOT) and ignoring release/1.0.x (1.0.0-rc)"]);
+    }
+    #[test]
+    fn equally_near_sources_with_the_same_version_are_not_reported()
+    {
+        let writer = RecordingWriter::default();
+        let git_dir = std::env::current_dir().unwrap().join("../test_data/sources/same-version");
+        let solution = solve(&writer, &git_dir, get_config(), test_args(git_dir.clone())).unwrap();
+        assert_eq!(solution.version.to_string(), "1.0.0-feat-same-version.1");
+        assert!(writer.0.borrow().is_empty());
+    }
+    #[test]
+    fn sources_are_solved_lazily_and_memoized()
+    {
+        let cfg = get_config().parse(SemVerScheme::default()).unwrap();
+        let repo = git2::Repository::open(std::env::current_dir().unwrap().join("../test_data/releaseflow/1.0.0-fix-patch-something.1")).unwrap();
+        let refs = crate::RepoRefs::collect(&NullWriter, &cfg, &repo, crate::BranchRefs::All).unwrap();
+        let solver = ||
+        {
+            let head = repo.head().unwrap().target().unwrap();
+            let branch = cfg.try_match_branch("fix/patch-something", head).unwrap().unwrap();
+            crate::BranchSolver::new(crate::BranchSolveContext { depth: 0, max_depth: u32::MAX }, &NullWriter, &cfg, &repo, &refs, branch).unwrap()
+        };
+
+        let mut first = solver();
+        assert!(refs.solved.borrow().is_empty());
+        assert_eq!(first.solve().unwrap().0.to_string(), "1.0.0-fix-patch-something.1");
+
+        let mut solved: Vec<(String, String)> = refs.solved.borrow().keys().map(|(t, name, _)| (t.clone(), name.clone())).collect();
+        solved.sort();
+        // the release branch has a base version, so main is never solved
+        assert_eq!(solved, vec![("release".to_string(), "release/1.0.x".to_string())]);
+
+        let merge_bases = refs.merge_bases.borrow().len();
+        assert_eq!(solver().solve().unwrap().0.to_string(), "1.0.0-fix-patch-something.1");
+        assert_eq!(refs.solved.borrow().len(), 1);
+        // the second solver of the same tip reuses the merge bases of the first
+        assert_eq!(refs.merge_bases.borrow().len(), merge_bases);
+
+        assert_eq!(solver().explain().unwrap().version.to_string(), "1.0.0-fix-patch-something.1");
+        assert_eq!(solver().explain().unwrap().version.to_string(), "1.0.0-fix-patch-something.1");
+        assert_eq!(refs.explained.borrow().len(), 1);
+    }
+    #[test]
+    fn source_cycles_are_rejected()
+    {
+        let mut cfg = get_config();
+        cfg.branches.get_mut("main").unwrap().sources = vec!["feature".into()];
+        let err = cfg.parse(SemVerScheme::default()).err().expect("cycle not detected");
+        assert_eq!(err.to_string(), "branch sources form a cycle: feature -> main -> feature");
+
+        let mut cfg = get_config();
+        cfg.branches.get_mut("feature").unwrap().sources.push("feature".into());
+        let err = cfg.parse(SemVerScheme::default()).err().expect("cycle not detected");
+        assert_eq!(err.to_string(), "branch sources form a cycle: feature -> feature");
+
+        let mut cfg = get_config();
+        cfg.branches.get_mut("release").unwrap().tracked = vec!["feature".into()];
+        cfg.branches.get_mut("feature").unwrap().tracked = vec!["main".into()];
+        let err = cfg.parse(SemVerScheme::default()).err().expect("cycle not detected");
+        assert_eq!(err.to_string(), "tracked branches form a cycle: feature -> main -> release -> feature");
+    }
+    #[test]
+    fn source_cycles_are_detected_when_solving()
+    {
+        // bypass the check of the config to reach the check of the solver
+        let mut raw = get_config();
+        let main = raw.branches.get_mut("main").unwrap();
+        main.sources = vec!["feature".into()];
+        main.base_version = None;
+        let cfg = crate::config::Config
+        {
+            scheme: SemVerScheme::default(),
+            tracked_remotes: vec![],
+            ci_branch_variables: vec![],
+            paths: None,
+            tags: vec![],
+            branches: raw.branches.into_iter().map(|(name, b)| b.parse(name, SemVerScheme::default()).unwrap()).collect()
+        };
+
+        let repo = git2::Repository::open(std::env::current_dir().unwrap().join("../test_data/releaseflow/1.0.0-feat-on-root")).unwrap();
+        let refs = crate::RepoRefs::collect(&NullWriter, &cfg, &repo, crate::BranchRefs::All).unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let branch = cfg.try_match_branch("feature/on-root", head).unwrap().unwrap();
+        let mut solver = crate::BranchSolver::new(crate::BranchSolveContext { depth: 0, max_depth: u32::MAX }, &NullWriter, &cfg, &repo, &refs, branch).unwrap();
+
+        let err = solver.solve().unwrap_err();
+        assert_eq!(err.to_string(), format!("branch sources form a cycle: feature/on-root (feature) at {head} -> main (main) at {head} -> feature/on-root (feature) at {head}"));
+    }
+}
@@ -1,12 +1,12 @@
 use std::{fs, path::{Path, PathBuf}, process::ExitCode};
 
 use anyhow::bail;
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use config::RawConfig;
 use console::Console;
 use path_absolutize::Absolutize;
-use verner_core::output::LogLevel;
-use verner_git::cli::ConfigPreset;
+use verner_core::{format::OutputFormat, output::LogLevel, template::Template};
+use verner_git::{cli::{ConfigPreset, OutputMode}, Solution};
 
 mod console;
 mod config;
@@ -31,7 +31,7 @@ struct Args
 #[derive(Subcommand, Debug)]
 enum Subcommands
 {
-    Git(verner_git::cli::Args),
+    Git(Box<verner_git::cli::Args>),
     Init(InitArgs)
 }
 
@@ -51,6 +51,13 @@ enum InitType
 fn main() -> ExitCode 
 {
     let args = Args::parse();
+    if let Subcommands::Git(ref git) = args.command
+    {
+        if let Err(err) = git.check_output()
+        {
+            Args::command().error(ErrorKind::ArgumentConflict, err).exit();
+        }
+    }
     let mut console = Console::default();
 
     if args.trace
@@ -84,6 +91,18 @@ fn read_config(path: &Path) -> anyhow::Result<config::RawConfig>
     Ok(config)
 }
 
+/// the version of a solution as text, in the requested format or template
+fn render(solution: &Solution, format: Option<OutputFormat>, template: Option<&Template>) -> anyhow::Result<String>
+{
+    let version = solution.version.as_ref();
+    Ok(match (format, template)
+    {
+        (Some(format), _) => format.format(version)?,
+        (None, Some(template)) => template.render(|name| solution.variable(name))?,
+        (None, None) => solution.text.clone(),
+    })
+}
+
 fn run(console: &Console, args: Args) -> anyhow::Result<()>
 {
     let cwd = args.path.absolutize()?;
@@ -96,17 +115,59 @@ fn run(console: &Console, args: Args) -> anyhow::Result<()>
         {
 
             let config = if let Some(ref preset) = git.config_preset { RawConfig { git: verner_git::preset_config(preset)? } } else { read_config(&config_path)? };
-            let version = verner_git::solve(console, &cwd, config.git, git)?;
+            let format = git.format;
+            let template = git.template.clone();
+            let output = git.output_mode();
+
+            if git.all_components
+            {
+                let solutions = verner_git::solve_all_components(console, &cwd, config.git, *git)?;
+
+                if solutions.values().any(|s| s.explanation.is_some())
+                {
+                    for (name, solution) in solutions.iter()
+                    {
+                        console.output(format!("{name}:"));
+                        if let Some(ref explanation) = solution.explanation
+                        {
+                            console.output(explanation);
+                        }
+                    }
+                    return Ok(());
+                }
+
+                match output
+                {
+                    OutputMode::Json => console.output(serde_json::to_string_pretty(&solutions)?),
+                    OutputMode::Text => for (name, solution) in solutions.iter()
+                    {
+                        console.output(format!("{name}: {}", render(solution, format, template.as_ref())?));
+                    },
+                }
+                return Ok(());
+            }
+
+            let solution = verner_git::solve(console, &cwd, config.git, *git)?;
             
-            console.user_line(LogLevel::Info, format!("Version: {version}"));
-            console.output(version);
+            console.user_line(LogLevel::Info, format!("Version: {}", solution.text));
+            if let Some(ref explanation) = solution.explanation
+            {
+                console.output(explanation);
+                return Ok(());
+            }
+
+            match output
+            {
+                OutputMode::Json => console.output(serde_json::to_string_pretty(&solution)?),
+                OutputMode::Text => console.output(render(&solution, format, template.as_ref())?),
+            }
         },
         Subcommands::Init(init) => 
         {
 
             if config_path.exists()
             {
-                console.user_line(LogLevel::Error, format!("cannot overwrite existing config file"));
+                console.user_line(LogLevel::Error, "cannot overwrite existing config file");
                 bail!("file {} already exists", config_path.to_string_lossy());
             }
 